@@ -2,15 +2,49 @@ use crate::*;
 
 pub const NO_DEPOSIT: Balance = 0;
 pub const GAS_EXT_CALL_UNLOCK: u64 = 40_000_000_000_000;
+pub const GAS_EXT_CALL_SWEEP_TO: u64 = 10_000_000_000_000;
+pub const GAS_EXT_CALL_AFTER_SWEEP: u64 = 5_000_000_000_000;
 pub const GAS_EXT_CALL_CLEAN_UP: u64 = 200_000_000_000_000;
-pub const GAS_EXT_CALL_GET_OWNER: u64 = 40_000_000_000_000;
+pub const GAS_EXT_CALL_CLEAN_UP_MANY: u64 = 30_000_000_000_000;
+pub const GAS_LOT_CLAIM_MANY_RESERVE: u64 = 20_000_000_000_000;
+pub const GAS_EXT_CALL_GET_LOCK_INFO: u64 = 40_000_000_000_000;
 pub const GAS_EXT_CALL_AFTER_REMOVE_UNSAFE: u64 = 100_000_000_000_000;
+pub const GAS_EXT_CALL_GET_LOCK_INFO_BUNDLED: u64 = 15_000_000_000_000;
+pub const GAS_EXT_CALL_AFTER_BUNDLE_JOIN: u64 = 20_000_000_000_000;
+pub const GAS_EXT_CALL_GET_OWNER: u64 = 20_000_000_000_000;
+pub const GAS_EXT_CALL_AFTER_REMOVE_UNSAFE_GET_OWNER: u64 = 60_000_000_000_000;
+pub const GAS_EXT_CALL_AFTER_RECONCILE: u64 = 180_000_000_000_000;
+pub const GAS_EXT_CALL_AFTER_RECONCILE_GET_OWNER: u64 = 60_000_000_000_000;
+
+pub const LOT_CLEAN_UP_STEP_MAX_ITEMS: u64 = 100;
+pub const LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY: u64 = 10;
 
 pub const ERR_LOT_CLEAN_UP_STILL_ACTIVE: &str = "UNREACHABLE: cannot clean up still active lot";
-pub const ERR_LOT_CLEAN_UP_UNLOCK_FAILED: &str = "Expected unlock promise to be successful";
+pub const ERR_LOT_BID_WRONG_REFERRER: &str = "lot_bid: bidder, seller and lot cannot refer";
+pub const ERR_LOT_PREV_BIDDER_COMMISSION_SHARE_OUT_OF_BOUNDS: &str =
+    "lot_offer: prev bidder commission share out of bounds";
+pub const ERR_LOT_BID_STEP_OUT_OF_BOUNDS: &str = "lot_offer: bid step out of bounds";
+pub const ERR_LOT_BID_STEP_MIN_INCREMENT_TOO_BIG: &str =
+    "lot_offer: bid step min increment too big";
+pub const ERR_LOT_RELIST_TOO_MANY_ROUNDS: &str = "lot_offer: too many relist rounds";
+pub const ERR_LOT_OFFER_BUNDLED: &str = "lot_offer: account is bundled with another lot";
+pub const ERR_LOT_BUNDLE_JOIN_LOT_EXISTS: &str = "lot_bundle_join: account is offered as a lot";
+pub const ERR_LOT_BUNDLE_JOIN_ALREADY_BUNDLED: &str = "lot_bundle_join: account is already bundled";
+pub const ERR_LOT_AFTER_CLAIM_RESULTS_MISMATCH: &str =
+    "UNREACHABLE: expected unlock result for each locked account";
+pub const ERR_LOT_CLAIM_MANY_EMPTY: &str = "lot_claim_many: expected lots to claim";
+pub const ERR_LOT_CLAIM_MANY_NOT_ENOUGH_GAS: &str = "lot_claim_many: not enough gas attached";
 pub const ERR_INTERNAL_LOT_SAVE_ALREADY_EXISTS: &str = "internal_lot_save: lot already exists";
 pub const ERR_INTERNAL_LOT_EXTRACT_NOT_EXIST: &str = "internal_lot_extract: lot does not exist";
+pub const ERR_LOT_CLEAN_UP_STEP_NOT_CLEANING_UP: &str = "lot_clean_up_step: lot is not cleaning up";
+pub const ERR_LOT_CANCEL_WITH_PENALTY_DEPOSIT: &str =
+    "lot_cancel_with_penalty: deposit not accepted";
+pub const ERR_LOT_CANCEL_WITH_PENALTY_NOT_ENOUGH_REWARDS: &str =
+    "lot_cancel_with_penalty: not enough seller rewards";
+pub const ERR_LOT_RECONCILE_STILL_ACTIVE: &str = "lot_reconcile: lot is still active";
+pub const ERR_LOT_RECONCILE_CLEANING_UP: &str = "lot_reconcile: lot is cleaning up";
 pub const ERR_LOT_REMOVE_UNSAFE_LOT_HAS_BIDS: &str = "lot_remove_unsafe: lot has bids";
+pub const ERR_LOT_REMOVE_UNSAFE_CLEANING_UP: &str = "lot_remove_unsafe: lot is cleaning up";
 pub const ERR_LOT_REMOVE_UNSAFE_LOT_SEEMS_SAFE: &str = "lot_remove_unsafe: lot seems safe";
 pub const ERR_LOT_REMOVE_UNSAFE_LOT_ON_GRACE_PERIOD: &str =
     "lot_remove_unsafe: lot on grace period, wait";
@@ -21,6 +55,7 @@ pub struct LotView {
     pub lot_id: LotId,
     pub seller_id: ProfileId,
     pub last_bidder_id: Option<ProfileId>,
+    pub claimer_id: Option<ProfileId>,
     pub reserve_price: WrappedBalance,
     pub buy_now_price: WrappedBalance,
     pub start_timestamp: WrappedTimestamp,
@@ -29,6 +64,18 @@ pub struct LotView {
     pub next_bid_amount: Option<WrappedBalance>,
     pub is_active: bool,
     pub is_withdrawn: bool,
+    pub is_cancelled: bool,
+    pub sweep_on_claim: bool,
+    pub payouts: Vec<(ProfileId, FractionView)>,
+    pub prev_bidder_commission_share: FractionView,
+    pub bid_step: FractionView,
+    pub bid_step_min_increment: WrappedBalance,
+    pub relist_round: u32,
+    pub relist_rounds_left: u32,
+    pub relist_price_drop: Option<FractionView>,
+    pub bundle_ids: Vec<LotId>,
+    pub unlocked_ids: Vec<LotId>,
+    pub is_raffle: bool,
     pub status: String,
 }
 
@@ -37,30 +84,60 @@ impl From<(&Lot, Timestamp, &Contract)> for LotView {
     fn from(args: (&Lot, Timestamp, &Contract)) -> Self {
         let (lot, now, contract) = args;
         let last_bid = lot.last_bid();
+        let prev_bidder_commission_share = contract.internal_lot_prev_bidder_commission_share(lot);
+        let (start_timestamp, finish_timestamp, reserve_price) = lot.relist_round_state(now);
+        let relist_policy = lot.relist_policy_at(now);
 
         Self {
             lot_id: lot.lot_id.clone(),
             seller_id: lot.seller_id.clone(),
             last_bidder_id: last_bid.as_ref().map(|x| x.bidder_id.clone()),
-            reserve_price: lot.reserve_price.into(),
+            claimer_id: lot.potential_claimer_id(),
+            reserve_price: reserve_price.into(),
             buy_now_price: lot.buy_now_price.into(),
-            start_timestamp: lot.start_timestamp.into(),
-            finish_timestamp: lot.finish_timestamp.into(),
+            start_timestamp: start_timestamp.into(),
+            finish_timestamp: finish_timestamp.into(),
             last_bid_amount: last_bid.as_ref().map(|x| x.amount.into()),
             next_bid_amount: lot
                 .next_bid_amount(now, contract.bid_step)
                 .map(|x| x.into()),
             is_active: lot.is_active(now),
             is_withdrawn: lot.is_withdrawn,
+            is_cancelled: lot.is_cancelled,
+            sweep_on_claim: lot.sweep_on_claim,
+            payouts: lot
+                .payouts
+                .iter()
+                .map(|(beneficiary_id, share)| (beneficiary_id.clone(), share.into()))
+                .collect(),
+            prev_bidder_commission_share: (&prev_bidder_commission_share).into(),
+            bid_step: (&lot.bid_step.unwrap_or(contract.bid_step)).into(),
+            bid_step_min_increment: lot.bid_step_min_increment.into(),
+            relist_round: relist_policy.map(|x| x.round).unwrap_or(0),
+            relist_rounds_left: relist_policy.map(|x| x.rounds_left).unwrap_or(0),
+            relist_price_drop: relist_policy.map(|x| (&x.price_drop).into()),
+            bundle_ids: lot.bundle_ids.clone(),
+            unlocked_ids: lot.unlocked_ids.clone(),
+            is_raffle: lot.raffle.is_some(),
             status: lot.status(now).to_string(),
         }
     }
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RaffleView {
+    pub ticket_price: WrappedBalance,
+    pub tickets_count: u64,
+    pub has_ticket: bool,
+    pub winner_id: Option<ProfileId>,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BidView {
     pub bidder_id: ProfileId,
+    pub beneficiary_id: Option<ProfileId>,
     pub amount: WrappedBalance,
     pub timestamp: WrappedTimestamp,
 }
@@ -69,6 +146,7 @@ impl From<&Bid> for BidView {
     fn from(bid: &Bid) -> Self {
         Self {
             bidder_id: bid.bidder_id.clone(),
+            beneficiary_id: bid.beneficiary_id.clone(),
             amount: bid.amount.into(),
             timestamp: bid.timestamp.into(),
         }
@@ -76,10 +154,15 @@ impl From<&Bid> for BidView {
 }
 
 impl Contract {
+    // lots expired without bids are restarted here according to their relist policy
     pub(crate) fn internal_lot_extract(&mut self, lot_id: &LotId) -> Lot {
-        self.lots
+        let mut lot = self
+            .lots
             .remove(lot_id)
-            .expect(ERR_INTERNAL_LOT_EXTRACT_NOT_EXIST)
+            .expect(ERR_INTERNAL_LOT_EXTRACT_NOT_EXIST);
+        lot.touch(env::block_timestamp());
+
+        lot
     }
 
     pub(crate) fn internal_lot_save(&mut self, lot: &Lot) {
@@ -90,6 +173,231 @@ impl Contract {
         );
     }
 
+    // lot must be already extracted from lots storage, returns true if clean up is finished
+    pub(crate) fn internal_lot_clean_up(&mut self, lot: Lot, max_items: u64) -> bool {
+        if !lot.is_cleaning_up {
+            let mut seller = self.internal_profile_extract(&lot.seller_id);
+            seller.lots_offering.remove(&lot.lot_id);
+            self.internal_profile_save(&seller);
+
+            if let Some(claimer_id) = lot.claimer_id_override.as_ref() {
+                let mut claimer = self.internal_profile_extract(claimer_id);
+                claimer.lots_bidding.remove(&lot.lot_id);
+                self.internal_profile_save(&claimer);
+            }
+
+            for account_id in lot.bundle_ids.iter() {
+                self.lot_bundle_members.remove(account_id);
+            }
+        }
+
+        self.internal_lot_clean_up_step(lot, max_items)
+    }
+
+    // removes up to max_items bids with bidder associations, then watchers with watchlist entries,
+    // lot is saved back until all bids and watchers are removed. The lot pending relist keeps
+    // the watchers and is relisted once the bids are removed
+    pub(crate) fn internal_lot_clean_up_step(&mut self, mut lot: Lot, max_items: u64) -> bool {
+        let bids = lot.clean_up_step(max_items);
+        let watchers = if lot.relist_pending.is_some() {
+            vec![]
+        } else {
+            lot.clean_up_watchers_step(max_items - bids.len() as u64)
+        };
+        for bid in bids.iter() {
+            self.internal_storage_release(&bid.bidder_id, bid.storage_usage);
+        }
+        let bidder_ids_unique: HashSet<ProfileId> =
+            bids.into_iter().flat_map(|x| x.account_ids()).collect();
+
+        bidder_ids_unique.iter().for_each(|bidder_id| {
+            let mut profile = self.internal_profile_extract(bidder_id);
+            profile.lots_bidding.remove(&lot.lot_id);
+            self.internal_profile_save(&profile);
+        });
+
+        watchers.iter().for_each(|(watcher_id, storage_usage)| {
+            let mut profile = self.internal_profile_extract(watcher_id);
+            profile.lots_watching.remove(&lot.lot_id);
+            self.internal_profile_save(&profile);
+            self.internal_storage_release(watcher_id, *storage_usage);
+        });
+
+        if lot.relist_pending.is_some() && lot.bids_count() == 0 {
+            let lot_id = lot.lot_id.clone();
+            self.internal_lot_save(&lot);
+            let storage_usage = env::storage_usage();
+            let mut lot = self.internal_lot_extract(&lot_id);
+
+            let relist = lot.relist_pending.take().unwrap();
+            let start_timestamp = env::block_timestamp();
+            log!("clean_up: lot {} relisted by {}", lot_id, relist.seller_id);
+            self.internal_lot_relist(
+                lot,
+                &relist.seller_id,
+                relist.reserve_price,
+                relist.buy_now_price,
+                start_timestamp,
+                start_timestamp + relist.duration,
+                vec![],
+                relist.prev_bidder_commission_share,
+                (None, 0),
+            );
+            self.internal_storage_charge_lot(&lot_id, storage_usage);
+            true
+        } else if lot.is_clean_up_finished() {
+            // intentionally not inserting the lot back
+            self.internal_storage_release(lot.storage_payer_id(), lot.storage_usage);
+            true
+        } else {
+            log!("clean_up: lot {} has bids left", lot.lot_id);
+            self.internal_lot_save(&lot);
+            false
+        }
+    }
+
+    // builds unlock and clean up chain for the lot with claim already pending,
+    // accounts of the bundle still locked are unlocked in parallel
+    fn internal_lot_claim(
+        &self,
+        lot: &Lot,
+        claimer_id: &ProfileId,
+        public_key: PublicKey,
+        new_code: Option<Base64VecU8>,
+        gas_clean_up: u64,
+        clean_up_max_items: u64,
+    ) -> Promise {
+        let unlocks = lot
+            .account_ids_locked()
+            .into_iter()
+            .map(|account_id| {
+                let unlock = ext_lock_contract::unlock(
+                    public_key.clone(),
+                    new_code.clone(),
+                    account_id.clone(),
+                    NO_DEPOSIT,
+                    GAS_EXT_CALL_UNLOCK.into(),
+                );
+                if account_id == lot.lot_id && lot.sweep_on_claim && claimer_id != &lot.seller_id {
+                    log!(
+                        "lot_claim: sweeping {} balance to {}",
+                        lot.lot_id,
+                        lot.seller_id
+                    );
+                    ext_lock_contract::sweep_to(
+                        lot.seller_id.clone(),
+                        LOT_CLAIM_SWEEP_KEEP_AMOUNT.into(),
+                        lot.lot_id.clone(),
+                        NO_DEPOSIT,
+                        GAS_EXT_CALL_SWEEP_TO.into(),
+                    )
+                    .then(ext_self_contract::lot_after_claim_sweep(
+                        lot.lot_id.clone(),
+                        lot.seller_id.clone(),
+                        env::current_account_id(),
+                        NO_DEPOSIT,
+                        GAS_EXT_CALL_AFTER_SWEEP.into(),
+                    ))
+                    .then(unlock)
+                } else {
+                    unlock
+                }
+            })
+            .reduce(|acc, unlock| acc.and(unlock))
+            .unwrap();
+
+        // bundle unlocks take the gas of the clean up, the rest is left for lot_clean_up_step
+        let (gas_clean_up, clean_up_max_items) = if lot.bundle_ids.is_empty() {
+            (gas_clean_up, clean_up_max_items)
+        } else {
+            (
+                gas_clean_up.min(GAS_EXT_CALL_CLEAN_UP_MANY),
+                clean_up_max_items.min(LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY),
+            )
+        };
+
+        unlocks.then(ext_self_contract::lot_after_claim_clean_up(
+            lot.lot_id.clone(),
+            clean_up_max_items,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            gas_clean_up.into(),
+        ))
+    }
+
+    fn internal_lot_validate_bundle_join(
+        &self,
+        lot_id: &LotId,
+        account_id: &LotId,
+        time_now: Timestamp,
+    ) {
+        assert!(
+            self.lots.get(account_id).is_none(),
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_LOT_EXISTS,
+        );
+        assert!(
+            self.lot_bundle_members.get(account_id).is_none(),
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_ALREADY_BUNDLED,
+        );
+        let lot: Lot = self.lots.get(lot_id).unwrap();
+        lot.validate_bundle_join(account_id, time_now);
+    }
+
+    fn internal_lot_remove_unsafe(&mut self, lot_id: &LotId) -> bool {
+        let lot: Lot = self.internal_lot_extract(lot_id);
+        assert!(lot.last_bid().is_none());
+
+        self.internal_lot_clean_up(lot, LOT_CLEAN_UP_STEP_MAX_ITEMS);
+
+        true
+    }
+
+    fn internal_lot_reconcile(&mut self, lot_id: &LotId, is_owned: bool, max_items: u64) -> bool {
+        let time_now = env::block_timestamp();
+        let mut lot: Lot = self.internal_lot_extract(lot_id);
+        if is_owned {
+            if lot.is_claim_pending_expired(time_now) {
+                log!("lot_after_reconcile: lot still locked, claim reverted");
+                lot.claim_revert();
+            } else if lot.is_claim_pending {
+                log!("lot_after_reconcile: lot still locked, claim still in progress");
+            } else {
+                log!("lot_after_reconcile: lot still locked");
+            }
+            self.internal_lot_save(&lot);
+            return false;
+        }
+
+        log!("lot_after_reconcile: lot not owned, removing");
+        self.internal_lot_clean_up(lot, max_items);
+
+        true
+    }
+
+    fn internal_lot_seller_rewards_transfer(&mut self, lot: &Lot, amount: Balance) {
+        if lot.payouts.is_empty() {
+            self.internal_profile_rewards_transfer(&lot.seller_id, amount);
+            return;
+        }
+
+        for (beneficiary_id, value) in calc_payouts(amount, &lot.payouts) {
+            self.internal_profile_rewards_transfer(&beneficiary_id, value);
+        }
+    }
+
+    fn internal_lot_seller_rewards_withdraw(&mut self, lot: &Lot, amount: Balance) {
+        if lot.payouts.is_empty() {
+            self.internal_profile_rewards_withdraw(&lot.seller_id, amount);
+            return;
+        }
+
+        for (beneficiary_id, value) in calc_payouts(amount, &lot.payouts) {
+            self.internal_profile_rewards_withdraw(&beneficiary_id, value);
+        }
+    }
+
     fn calc_finish_timestamp(
         start_timestamp: Timestamp,
         finish_timestamp: Option<Timestamp>,
@@ -103,6 +411,83 @@ impl Contract {
         }
     }
 
+    pub(crate) fn internal_lot_prev_bidder_commission_share(&self, lot: &Lot) -> Fraction {
+        lot.prev_bidder_commission_share
+            .unwrap_or(self.prev_bidder_commission_share)
+    }
+
+    fn internal_validate_prev_bidder_commission_share(
+        &self,
+        share: Option<FractionView>,
+    ) -> Option<Fraction> {
+        share.map(|share| {
+            let share = Fraction::new(share.num, share.denom);
+            assert!(
+                self.prev_bidder_commission_share_min <= share
+                    && share <= self.prev_bidder_commission_share_max,
+                "{}",
+                ERR_LOT_PREV_BIDDER_COMMISSION_SHARE_OUT_OF_BOUNDS,
+            );
+            share
+        })
+    }
+
+    fn internal_validate_bid_step(
+        &self,
+        bid_step: Option<FractionView>,
+        bid_step_min_increment: Option<WrappedBalance>,
+    ) -> (Option<Fraction>, Balance) {
+        let bid_step = bid_step.map(|bid_step| {
+            let bid_step = Fraction::new(bid_step.num, bid_step.denom);
+            assert!(
+                self.bid_step_min <= bid_step && bid_step <= self.bid_step_max,
+                "{}",
+                ERR_LOT_BID_STEP_OUT_OF_BOUNDS,
+            );
+            bid_step
+        });
+        let bid_step_min_increment: Balance = bid_step_min_increment.map(|x| x.into()).unwrap_or(0);
+        assert!(
+            bid_step_min_increment <= self.bid_step_min_increment_max,
+            "{}",
+            ERR_LOT_BID_STEP_MIN_INCREMENT_TOO_BIG,
+        );
+
+        (bid_step, bid_step_min_increment)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn internal_lot_relist(
+        &mut self,
+        mut lot: Lot,
+        seller_id: &ProfileId,
+        reserve_price: Balance,
+        buy_now_price: Balance,
+        start_timestamp: Timestamp,
+        finish_timestamp: Timestamp,
+        payouts: Vec<(ProfileId, Fraction)>,
+        prev_bidder_commission_share: Option<Fraction>,
+        bid_step: (Option<Fraction>, Balance),
+    ) {
+        lot.relist(
+            seller_id.clone(),
+            reserve_price,
+            buy_now_price,
+            start_timestamp,
+            finish_timestamp,
+        );
+        lot.payouts = payouts;
+        lot.prev_bidder_commission_share = prev_bidder_commission_share;
+        lot.bid_step = bid_step.0;
+        lot.bid_step_min_increment = bid_step.1;
+        self.internal_lot_save(&lot);
+
+        let mut profile = self.internal_profile_extract(seller_id);
+        profile.lots_offering.insert(&lot.lot_id);
+        self.internal_profile_save(&profile);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn internal_lot_offer(
         &mut self,
         lot_id: &LotId,
@@ -111,8 +496,15 @@ impl Contract {
         buy_now_price: Balance,
         start_timestamp: Timestamp,
         finish_timestamp: Timestamp,
+        sweep_on_claim: bool,
+        payouts: Vec<(ProfileId, Fraction)>,
+        prev_bidder_commission_share: Option<Fraction>,
+        bid_step: (Option<Fraction>, Balance),
+        relist_policy: Option<RelistPolicy>,
+        bundle_ids: Vec<LotId>,
+        raffle: Option<Raffle>,
     ) {
-        let lot = Lot::new(
+        let mut lot = Lot::new(
             lot_id.clone(),
             seller_id.clone(),
             reserve_price,
@@ -120,8 +512,20 @@ impl Contract {
             start_timestamp,
             finish_timestamp,
         );
+        lot.sweep_on_claim = sweep_on_claim;
+        lot.payouts = payouts;
+        lot.prev_bidder_commission_share = prev_bidder_commission_share;
+        lot.bid_step = bid_step.0;
+        lot.bid_step_min_increment = bid_step.1;
+        lot.relist_policy = relist_policy;
+        lot.bundle_ids = bundle_ids;
+        lot.raffle = raffle;
         self.internal_lot_save(&lot);
 
+        for account_id in lot.bundle_ids.iter() {
+            self.lot_bundle_members.insert(account_id, lot_id);
+        }
+
         // update associations
         {
             let mut profile = self.internal_profile_extract(seller_id);
@@ -135,6 +539,10 @@ impl Contract {
 impl Contract {
     pub fn lot_bid_list(&self, lot_id: LotId) -> Vec<BidView> {
         let lot: Lot = self.lots.get(&lot_id).unwrap();
+        if lot.is_cleaning_up {
+            // bid history is partially removed already
+            return vec![];
+        }
 
         lot.bids().iter().map(|v| v.into()).collect()
     }
@@ -148,18 +556,19 @@ impl Contract {
 
         let values_as_vector = self.lots.values_as_vector();
 
+        // lots being cleaned up are skipped, page may contain less than limit items
         (idx_from..idx_to)
-            .map(|x| {
-                let v = values_as_vector.get(x).unwrap();
-                (&v, now, self).into()
-            })
+            .map(|x| values_as_vector.get(x).unwrap())
+            .filter(|x| !x.is_cleaning_up)
+            .map(|x| (&x, now, self).into())
             .collect()
     }
 
     pub fn lot_get(&self, lot_id: LotId) -> Option<LotView> {
         let now = env::block_timestamp();
         let lot: Option<Lot> = self.lots.get(&lot_id);
-        lot.map(|x| (&x, now, self).into())
+        lot.filter(|x| !x.is_cleaning_up)
+            .map(|x| (&x, now, self).into())
     }
 
     pub fn lot_list_offering_by(
@@ -176,12 +585,14 @@ impl Contract {
         let idx_to = limit.map(|x| idx_from + x).unwrap_or(u64::MAX);
         let idx_to = std::cmp::min(idx_to, vector.len());
 
+        // lots being cleaned up are skipped, page may contain less than limit items
         (idx_from..idx_to)
             .map(|idx| {
                 let lot_id = vector.get(idx).unwrap();
-                let lot = self.lots.get(&lot_id).unwrap();
-                (&lot, time_now, self).into()
+                self.lots.get(&lot_id).unwrap()
             })
+            .filter(|lot| !lot.is_cleaning_up)
+            .map(|lot| (&lot, time_now, self).into())
             .collect()
     }
 
@@ -199,15 +610,25 @@ impl Contract {
         let idx_to = limit.map(|x| idx_from + x).unwrap_or(u64::MAX);
         let idx_to = std::cmp::min(idx_to, vector.len());
 
+        // lots being cleaned up are skipped, page may contain less than limit items
         (idx_from..idx_to)
             .map(|idx| {
                 let lot_id = vector.get(idx).unwrap();
-                let lot = self.lots.get(&lot_id).unwrap();
-                (&lot, time_now, self).into()
+                self.lots.get(&lot_id).unwrap()
             })
+            .filter(|lot| !lot.is_cleaning_up)
+            .map(|lot| (&lot, time_now, self).into())
             .collect()
     }
 
+    // Storage used by the offer is charged to the seller storage balance.
+    // sweep_on_claim: transfer the lot account balance to the seller before unlocking it for buyer
+    // payouts: seller proceeds shares summing to one, the first beneficiary gets the rounding dust
+    // prev_bidder_commission_share: outbid reward share, within contract min and max
+    // bid_step, bid_step_min_increment: next bid is at least the greater of the two increments
+    // relist_policy: (rounds, price_drop), restart the lot expired without bids up to rounds
+    // times with the same duration, reserve price reduced by price_drop each time
+    #[allow(clippy::too_many_arguments)]
     pub fn lot_offer(
         &mut self,
         seller_id: ProfileId,
@@ -215,8 +636,20 @@ impl Contract {
         buy_now_price: WrappedBalance,
         finish_timestamp: Option<WrappedTimestamp>,
         duration: Option<WrappedDuration>,
+        sweep_on_claim: Option<bool>,
+        payouts: Option<Vec<(ProfileId, FractionView)>>,
+        prev_bidder_commission_share: Option<FractionView>,
+        bid_step: Option<FractionView>,
+        bid_step_min_increment: Option<WrappedBalance>,
+        relist_policy: Option<(u32, FractionView)>,
     ) -> bool {
         let lot_id: LotId = env::predecessor_account_id();
+        let storage_usage = env::storage_usage();
+        assert!(
+            self.lot_bundle_members.get(&lot_id).is_none(),
+            "{}",
+            ERR_LOT_OFFER_BUNDLED,
+        );
         let reserve_price: Balance = reserve_price.into();
         let buy_now_price: Balance = buy_now_price.into();
         let start_timestamp: Timestamp = env::block_timestamp();
@@ -226,6 +659,33 @@ impl Contract {
             finish_timestamp.map(|x| x.into()),
             duration.map(|x| x.0),
         );
+        let payouts: Vec<(ProfileId, Fraction)> = payouts
+            .map(|payouts| {
+                let payouts: Vec<(ProfileId, Fraction)> = payouts
+                    .into_iter()
+                    .map(|(beneficiary_id, share)| {
+                        (beneficiary_id, Fraction::new(share.num, share.denom))
+                    })
+                    .collect();
+                validate_payouts(&payouts);
+                payouts
+            })
+            .unwrap_or_default();
+        let prev_bidder_commission_share =
+            self.internal_validate_prev_bidder_commission_share(prev_bidder_commission_share);
+        let bid_step = self.internal_validate_bid_step(bid_step, bid_step_min_increment);
+        let relist_policy = relist_policy.map(|(rounds, price_drop)| {
+            assert!(
+                rounds <= LOT_RELIST_MAX_ROUNDS,
+                "{}",
+                ERR_LOT_RELIST_TOO_MANY_ROUNDS,
+            );
+            RelistPolicy {
+                round: 0,
+                rounds_left: rounds,
+                price_drop: Fraction::new(price_drop.num, price_drop.denom),
+            }
+        });
 
         self.internal_lot_offer(
             &lot_id,
@@ -234,148 +694,693 @@ impl Contract {
             buy_now_price,
             start_timestamp,
             finish_timestamp,
+            sweep_on_claim.unwrap_or(false),
+            payouts,
+            prev_bidder_commission_share,
+            bid_step,
+            relist_policy,
+            vec![],
+            None,
+        );
+        self.internal_storage_charge_lot(&lot_id, storage_usage);
+
+        true
+    }
+
+    // Offers the lot as a fixed price raffle, one ticket per participant.
+    // The winner is drawn with lot_raffle_draw after finish_timestamp.
+    pub fn lot_offer_raffle(
+        &mut self,
+        seller_id: ProfileId,
+        ticket_price: WrappedBalance,
+        finish_timestamp: Option<WrappedTimestamp>,
+        duration: Option<WrappedDuration>,
+    ) -> bool {
+        let lot_id: LotId = env::predecessor_account_id();
+        let storage_usage = env::storage_usage();
+        assert!(
+            self.lot_bundle_members.get(&lot_id).is_none(),
+            "{}",
+            ERR_LOT_OFFER_BUNDLED,
+        );
+        let ticket_price: Balance = ticket_price.into();
+        let start_timestamp: Timestamp = env::block_timestamp();
+        let finish_timestamp = Self::calc_finish_timestamp(
+            start_timestamp,
+            finish_timestamp.map(|x| x.into()),
+            duration.map(|x| x.0),
+        );
+
+        self.internal_lot_offer(
+            &lot_id,
+            &seller_id,
+            ticket_price,
+            ticket_price,
+            start_timestamp,
+            finish_timestamp,
+            false,
+            vec![],
+            None,
+            (None, 0),
+            None,
+            vec![],
+            Some(Raffle {
+                ticket_price,
+                winner_id: None,
+            }),
         );
+        self.internal_storage_charge_lot(&lot_id, storage_usage);
 
         true
     }
 
     #[payable]
-    pub fn lot_bid(&mut self, lot_id: ProfileId) -> bool {
-        let bidder_id: ProfileId = env::predecessor_account_id();
-        let amount: Balance = env::attached_deposit();
-        let timestamp = env::block_timestamp();
-        let bid: Bid = Bid {
-            bidder_id: bidder_id.clone(),
-            amount,
-            timestamp,
+    pub fn lot_raffle_buy_ticket(&mut self, lot_id: LotId) -> bool {
+        let buyer_id: ProfileId = env::predecessor_account_id();
+        let storage_usage = env::storage_usage();
+        let ticket: Bid = Bid {
+            bidder_id: buyer_id.clone(),
+            amount: env::attached_deposit(),
+            timestamp: env::block_timestamp(),
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
 
         let mut lot = self.internal_lot_extract(&lot_id);
-        let prev_bid: Option<Bid> = lot.last_bid();
-        lot.place_bid(&bid, self.bid_step);
+        lot.buy_ticket(&ticket);
         self.internal_lot_save(&lot);
 
         // update associations
-        let mut bidder = self.internal_profile_extract(&bidder_id);
-        bidder.lots_bidding.insert(&lot_id);
-        self.internal_profile_save(&bidder);
+        let mut buyer = self.internal_profile_extract(&buyer_id);
+        buyer.lots_bidding.insert(&lot_id);
+        self.internal_profile_save(&buyer);
+        let storage_charged = self.internal_storage_charge(&buyer_id, storage_usage);
 
-        let (to_prev_bidder, to_seller) = calc_lot_bid_rewards(
-            prev_bid.as_ref().map(|x| x.amount),
-            bid.amount,
+        let mut lot = self.internal_lot_extract(&lot_id);
+        lot.last_bid_storage_charged(storage_charged);
+        self.internal_lot_save(&lot);
+
+        true
+    }
+
+    // Draws the raffle winner after finish_timestamp, callable by anyone.
+    // The winning ticket goes to the seller as a regular bid, other tickets are refunded.
+    // The winner is picked with the random seed of the block executing the draw, the caller
+    // cannot predict it, but the block producer can. The raffle trusts validators not to
+    // collude with participants, the seed and the tickets count are logged for verification.
+    pub fn lot_raffle_draw(&mut self, lot_id: LotId) -> ProfileId {
+        let time_now = env::block_timestamp();
+        let seed = env::random_seed();
+        let mut lot = self.internal_lot_extract(&lot_id);
+        let (winning_ticket, refunds) = lot.draw(&seed, time_now);
+        self.internal_lot_save(&lot);
+
+        let (_, to_seller, _) = calc_lot_bid_rewards(
+            None,
+            winning_ticket.amount,
             self.seller_rewards_commission,
-            self.prev_bidder_commission_share,
+            &self.commission_schedule,
+            self.internal_lot_prev_bidder_commission_share(&lot),
+            None,
         );
-        if let Some(to_prev_bidder) = to_prev_bidder {
-            self.internal_profile_rewards_transfer(
-                &prev_bid.as_ref().unwrap().bidder_id,
-                to_prev_bidder,
-            );
+        self.internal_lot_seller_rewards_transfer(&lot, to_seller);
+        for ticket in refunds.iter() {
+            self.internal_profile_rewards_transfer(&ticket.bidder_id, ticket.amount);
         }
-        self.internal_profile_rewards_transfer(&lot.seller_id, to_seller);
 
-        true
+        log!(
+            "lot_raffle_draw: {} won by {} out of {} tickets, seed {:?}",
+            lot_id,
+            winning_ticket.bidder_id,
+            refunds.len() + 1,
+            seed
+        );
+
+        winning_ticket.bidder_id
     }
 
-    pub fn lot_claim(&mut self, lot_id: LotId, public_key: PublicKey) -> Promise {
-        let claimer_id: ProfileId = env::predecessor_account_id();
-        let time_now = env::block_timestamp();
-        let lot: Lot = self.lots.get(&lot_id).unwrap();
+    // account_id: participant to check the ticket for
+    pub fn lot_raffle_get(
+        &self,
+        lot_id: LotId,
+        account_id: Option<ProfileId>,
+    ) -> Option<RaffleView> {
+        let lot = self.lots.get(&lot_id)?;
+        let raffle = lot.raffle.as_ref()?;
+
+        Some(RaffleView {
+            ticket_price: raffle.ticket_price.into(),
+            tickets_count: lot.bids_count(),
+            has_ticket: account_id.map(|x| lot.has_ticket(&x)).unwrap_or(false),
+            winner_id: raffle.winner_id.clone(),
+        })
+    }
 
-        lot.validate_claim(&claimer_id, time_now);
+    // Adds the calling account, locked to the marketplace, to be sold together with the lot.
+    // Only the account itself can join, usually from the lock contract init with `bundle`
+    // provided. All bundled accounts are unlocked to the winner on claim.
+    pub fn lot_bundle_join(&mut self, lot_id: LotId) -> Promise {
+        let account_id: LotId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+        self.internal_lot_validate_bundle_join(&lot_id, &account_id, time_now);
 
-        ext_lock_contract::unlock(
-            public_key,
-            lot_id.clone(),
+        ext_lock_contract::get_lock_info(
+            account_id.clone(),
             NO_DEPOSIT,
-            GAS_EXT_CALL_UNLOCK.into(),
+            GAS_EXT_CALL_GET_LOCK_INFO.into(),
         )
-        .then(ext_self_contract::lot_after_claim_clean_up(
+        .then(ext_self_contract::lot_after_bundle_join(
             lot_id,
+            account_id,
             env::current_account_id(),
             NO_DEPOSIT,
-            GAS_EXT_CALL_CLEAN_UP.into(),
+            GAS_EXT_CALL_AFTER_BUNDLE_JOIN.into(),
         ))
     }
 
+    // Joins the account only if it is still locked to the marketplace
     #[private]
-    pub fn lot_after_claim_clean_up(&mut self, lot_id: LotId) -> bool {
-        assert!(is_promise_success(), "{}", ERR_LOT_CLEAN_UP_UNLOCK_FAILED);
+    pub fn lot_after_bundle_join(&mut self, lot_id: LotId, account_id: LotId) -> bool {
+        let is_owned: bool = match env::promise_result(0) {
+            PromiseResult::Successful(x) => serde_json::from_slice::<LockInfoView>(&x)
+                .map(|x| !x.is_unlocked && x.owner_id == env::current_account_id())
+                .unwrap_or(false),
+            _ => false,
+        };
+        if !is_owned {
+            log!(
+                "lot_after_bundle_join: {} is not locked to the marketplace",
+                account_id
+            );
+            return false;
+        }
+
         let time_now = env::block_timestamp();
-        let mut lot: Lot = self.internal_lot_extract(&lot_id);
+        let storage_usage = env::storage_usage();
+        self.internal_lot_validate_bundle_join(&lot_id, &account_id, time_now);
+        let mut lot = self.internal_lot_extract(&lot_id);
+        lot.bundle_join(&account_id, time_now);
+        self.internal_lot_save(&lot);
+        self.lot_bundle_members.insert(&account_id, &lot_id);
+        self.internal_storage_charge_lot(&lot_id, storage_usage);
 
-        assert!(
-            !lot.is_active(time_now),
-            "{}",
-            ERR_LOT_CLEAN_UP_STILL_ACTIVE
-        );
+        log!("lot_bundle_join: {} joined {}", account_id, lot_id);
 
-        let bidder_ids_unique: HashSet<ProfileId> = lot
-            .bids()
-            .into_iter()
-            .map(|x| x.bidder_id)
-            .collect();
+        true
+    }
 
-        bidder_ids_unique.iter().for_each(|bidder_id| {
-            // TODO: validate bid exists
-            let mut profile = self.internal_profile_extract(bidder_id);
-            profile.lots_bidding.remove(&lot_id);
+    // Storage used by the bid is charged to the bidder storage balance.
+    // public_key: key to install on the lot account, allows anyone to finalize the sale
+    // referrer_id: partner who brought the bidder, rewarded from the commission
+    // beneficiary_id: account to receive the lot instead of the bidder, e.g. exchange customer
+    #[payable]
+    pub fn lot_bid(
+        &mut self,
+        lot_id: ProfileId,
+        public_key: Option<PublicKey>,
+        referrer_id: Option<ProfileId>,
+        beneficiary_id: Option<ProfileId>,
+    ) -> bool {
+        let bidder_id: ProfileId = env::predecessor_account_id();
+        let storage_usage = env::storage_usage();
+        let amount: Balance = env::attached_deposit();
+        let timestamp = env::block_timestamp();
+        let mut bid: Bid = Bid {
+            bidder_id: bidder_id.clone(),
+            amount,
+            timestamp,
+            public_key,
+            referrer_id: referrer_id.clone(),
+            beneficiary_id,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
+        };
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        let prev_bid: Option<Bid> = lot.last_bid();
+        let (to_prev_bidder, to_seller, to_referrer) = calc_lot_bid_rewards(
+            prev_bid.as_ref().map(|x| x.amount),
+            bid.amount,
+            self.seller_rewards_commission,
+            &self.commission_schedule,
+            self.internal_lot_prev_bidder_commission_share(&lot),
+            referrer_id.as_ref().map(|_| self.referrer_commission_share),
+        );
+        bid.rewards = BidRewards {
+            to_prev_bidder: to_prev_bidder.unwrap_or(0),
+            to_seller,
+            to_referrer,
+        };
+        lot.place_bid(&bid, self.bid_step);
+        self.internal_lot_save(&lot);
+
+        // update associations
+        for account_id in bid.account_ids() {
+            let mut profile = self.internal_profile_extract(&account_id);
+            profile.lots_bidding.insert(&lot_id);
             self.internal_profile_save(&profile);
-        });
-        {
-            let mut seller = self.internal_profile_extract(&lot.seller_id);
-            seller.lots_offering.remove(&lot_id);
-            self.internal_profile_save(&seller);
         }
 
-        lot.clean_up();
-        // lot is already deleted from lots storage, returning to persist changes
+        if let Some(referrer_id) = referrer_id.as_ref() {
+            assert!(
+                referrer_id != &bidder_id
+                    && Some(referrer_id) != bid.beneficiary_id.as_ref()
+                    && referrer_id != &lot.seller_id
+                    && referrer_id != &lot_id,
+                "{}",
+                ERR_LOT_BID_WRONG_REFERRER,
+            );
+        }
 
-        // intentionally not inserting the lot back
-        true
-    }
+        if let Some(to_prev_bidder) = to_prev_bidder {
+            self.internal_profile_rewards_transfer(
+                &prev_bid.as_ref().unwrap().bidder_id,
+                to_prev_bidder,
+            );
+        }
+        self.internal_lot_seller_rewards_transfer(&lot, to_seller);
+        if let Some(referrer_id) = referrer_id.as_ref() {
+            self.internal_profile_referral_rewards_transfer(referrer_id, to_referrer);
+        }
+        let storage_charged = self.internal_storage_charge(&bidder_id, storage_usage);
 
-    pub fn lot_withdraw(&mut self, lot_id: LotId) -> bool {
-        let withdrawer_id: ProfileId = env::predecessor_account_id();
         let mut lot = self.internal_lot_extract(&lot_id);
-        lot.withdraw(&withdrawer_id);
+        lot.last_bid_storage_charged(storage_charged);
         self.internal_lot_save(&lot);
 
         true
     }
 
-    pub fn lot_reoffer(
-        &mut self,
-        lot_id: LotId,
-        reserve_price: WrappedBalance,
+    // Cancels the caller's last bid within LOT_BID_RETRACT_DURATION, rewards paid for the bid
+    // are taken back, the bid is refunded minus the penalty, which goes to the seller
+    pub fn lot_bid_retract(&mut self, lot_id: LotId) -> Promise {
+        let bidder_id: ProfileId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        let bid = lot.bid_retract(&bidder_id, time_now);
+        let prev_bid: Option<Bid> = lot.last_bid();
+        self.internal_lot_save(&lot);
+
+        // update associations, bidder and beneficiary may still have earlier bids on the lot
+        let bids = lot.bids();
+        for account_id in bid.account_ids() {
+            if !bids.iter().any(|x| x.account_ids().contains(&account_id)) {
+                let mut profile = self.internal_profile_extract(&account_id);
+                profile.lots_bidding.remove(&lot_id);
+                self.internal_profile_save(&profile);
+            }
+        }
+
+        self.internal_storage_release(&bid.bidder_id, bid.storage_usage);
+
+        let penalty = self.bid_retract_penalty * bid.amount;
+
+        // penalty is credited first, seller may have claimed the bid rewards already
+        self.internal_lot_seller_rewards_transfer(&lot, penalty);
+        self.internal_lot_seller_rewards_withdraw(&lot, bid.rewards.to_seller);
+        if let Some(prev_bid) = prev_bid.as_ref() {
+            self.internal_profile_rewards_withdraw(&prev_bid.bidder_id, bid.rewards.to_prev_bidder);
+        }
+        if let Some(referrer_id) = bid.referrer_id.as_ref() {
+            self.internal_profile_referral_rewards_withdraw(referrer_id, bid.rewards.to_referrer);
+        }
+
+        log!(
+            "lot_bid_retract: {} bid {} retracted by {}, penalty {}",
+            lot_id,
+            bid.amount,
+            bidder_id,
+            penalty
+        );
+
+        Promise::new(bidder_id).transfer(bid.amount - penalty)
+    }
+
+    // If new_code is provided, the lock contract removes itself and deploys new_code instead,
+    // empty new_code leaves the lot account as a plain account
+    pub fn lot_claim(
+        &mut self,
+        lot_id: LotId,
+        public_key: PublicKey,
+        new_code: Option<Base64VecU8>,
+    ) -> Promise {
+        let claimer_id: ProfileId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+        let mut lot: Lot = self.internal_lot_extract(&lot_id);
+        lot.claim(&claimer_id, time_now);
+        self.internal_lot_save(&lot);
+
+        self.internal_lot_claim(
+            &lot,
+            &claimer_id,
+            public_key,
+            new_code,
+            GAS_EXT_CALL_CLEAN_UP,
+            LOT_CLEAN_UP_STEP_MAX_ITEMS,
+        )
+    }
+
+    // Claims several lots at once, fails if any of the lots cannot be claimed.
+    // Each lot is unlocked and cleaned up independently, results are logged per lot.
+    pub fn lot_claim_many(&mut self, claims: Vec<(LotId, PublicKey)>) -> bool {
+        assert!(!claims.is_empty(), "{}", ERR_LOT_CLAIM_MANY_EMPTY);
+
+        let claimer_id: ProfileId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+        let lots: Vec<(Lot, PublicKey)> = claims
+            .into_iter()
+            .map(|(lot_id, public_key)| {
+                let mut lot: Lot = self.internal_lot_extract(&lot_id);
+                lot.claim(&claimer_id, time_now);
+                self.internal_lot_save(&lot);
+                (lot, public_key)
+            })
+            .collect();
+
+        // bundles need an unlock per account
+        let gas_required: u64 = lots
+            .iter()
+            .map(|(lot, _)| {
+                lot.account_ids_locked().len() as u64 * GAS_EXT_CALL_UNLOCK
+                    + GAS_EXT_CALL_SWEEP_TO
+                    + GAS_EXT_CALL_AFTER_SWEEP
+                    + GAS_EXT_CALL_CLEAN_UP_MANY
+            })
+            .sum::<u64>()
+            + GAS_LOT_CLAIM_MANY_RESERVE;
+        assert!(
+            u64::from(env::prepaid_gas()) >= gas_required,
+            "{}",
+            ERR_LOT_CLAIM_MANY_NOT_ENOUGH_GAS,
+        );
+
+        log!("lot_claim_many: claiming {} lots", lots.len());
+        for (lot, public_key) in lots {
+            self.internal_lot_claim(
+                &lot,
+                &claimer_id,
+                public_key,
+                None,
+                GAS_EXT_CALL_CLEAN_UP_MANY,
+                LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY,
+            );
+        }
+
+        true
+    }
+
+    // Claims the lot on behalf of the winner with the public key from the winning bid,
+    // callable by anyone after the sale is finished
+    pub fn lot_finalize(&mut self, lot_id: LotId) -> Promise {
+        let time_now = env::block_timestamp();
+        let mut lot: Lot = self.internal_lot_extract(&lot_id);
+        let public_key = lot.finalize(time_now);
+        self.internal_lot_save(&lot);
+
+        let claimer_id: ProfileId = lot.potential_claimer_id().unwrap();
+        log!("lot_finalize: finalizing {} for {}", lot_id, claimer_id);
+
+        self.internal_lot_claim(
+            &lot,
+            &claimer_id,
+            public_key,
+            None,
+            GAS_EXT_CALL_CLEAN_UP,
+            LOT_CLEAN_UP_STEP_MAX_ITEMS,
+        )
+    }
+
+    #[private]
+    pub fn lot_after_claim_clean_up(&mut self, lot_id: LotId, max_items: u64) -> bool {
+        let mut lot: Lot = self.internal_lot_extract(&lot_id);
+        let account_ids = lot.account_ids_locked();
+        let results = promise_results_unlocked();
+        assert_eq!(
+            account_ids.len(),
+            results.len(),
+            "{}",
+            ERR_LOT_AFTER_CLAIM_RESULTS_MISMATCH,
+        );
+        let unlocked_ids: Vec<LotId> = account_ids
+            .into_iter()
+            .zip(results)
+            .filter(|(_, is_unlocked)| *is_unlocked)
+            .map(|(account_id, _)| account_id)
+            .collect();
+        if !lot.claim_unlocked(unlocked_ids) {
+            // some accounts are still locked, allowing to claim them again
+            log!(
+                "lot_after_claim_clean_up: {} unlock failed for {:?}, claim reverted",
+                lot_id,
+                lot.account_ids_locked()
+            );
+            self.internal_lot_save(&lot);
+            return false;
+        }
+        let time_now = env::block_timestamp();
+
+        assert!(
+            !lot.is_active(time_now),
+            "{}",
+            ERR_LOT_CLEAN_UP_STILL_ACTIVE
+        );
+
+        log!("lot_after_claim_clean_up: {} claimed", lot_id);
+        self.internal_lot_clean_up(lot, max_items);
+
+        true
+    }
+
+    // Logs the amount swept from the lot account, the unlock goes on regardless
+    #[private]
+    pub fn lot_after_claim_sweep(
+        &mut self,
+        lot_id: LotId,
+        beneficiary_id: ProfileId,
+    ) -> WrappedBalance {
+        let amount: Balance = match env::promise_result(0) {
+            PromiseResult::Successful(x) => serde_json::from_slice::<WrappedBalance>(&x)
+                .map(|x| x.0)
+                .unwrap_or(0),
+            _ => 0,
+        };
+        log!(
+            "lot_after_claim_sweep: swept {} from {} to {}",
+            amount,
+            lot_id,
+            beneficiary_id
+        );
+
+        amount.into()
+    }
+
+    // Continues clean up of the lot with long bid history, callable by anyone
+    pub fn lot_clean_up_step(&mut self, lot_id: LotId, max_items: u64) -> bool {
+        let lot: Lot = self.internal_lot_extract(&lot_id);
+        assert!(
+            lot.is_cleaning_up,
+            "{}",
+            ERR_LOT_CLEAN_UP_STEP_NOT_CLEANING_UP,
+        );
+
+        self.internal_lot_clean_up_step(lot, max_items)
+    }
+
+    // Transfers the right to claim the won lot to another account
+    pub fn lot_transfer_claim(&mut self, lot_id: LotId, new_owner_id: ProfileId) -> bool {
+        let caller_id: ProfileId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+        let storage_usage = env::storage_usage();
+        let mut lot: Lot = self.internal_lot_extract(&lot_id);
+        lot.transfer_claim(&caller_id, &new_owner_id, time_now);
+        self.internal_lot_save(&lot);
+
+        // update associations
+        {
+            let mut prev_owner = self.internal_profile_extract(&caller_id);
+            prev_owner.lots_bidding.remove(&lot_id);
+            self.internal_profile_save(&prev_owner);
+        }
+        {
+            let mut new_owner = self.internal_profile_extract(&new_owner_id);
+            new_owner.lots_bidding.insert(&lot_id);
+            self.internal_profile_save(&new_owner);
+        }
+        self.internal_storage_charge(&caller_id, storage_usage);
+
+        log!(
+            "lot_transfer_claim: {} claim transferred from {} to {}",
+            lot_id,
+            caller_id,
+            new_owner_id
+        );
+
+        true
+    }
+
+    pub fn lot_withdraw(&mut self, lot_id: LotId) -> bool {
+        let withdrawer_id: ProfileId = env::predecessor_account_id();
+        let mut lot = self.internal_lot_extract(&lot_id);
+        lot.withdraw(&withdrawer_id);
+        self.internal_lot_save(&lot);
+
+        true
+    }
+
+    // Moves the deadline of the lot without bids, seller only
+    pub fn lot_extend(&mut self, lot_id: LotId, new_finish_timestamp: WrappedTimestamp) -> bool {
+        let seller_id: ProfileId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+        let new_finish_timestamp: Timestamp = new_finish_timestamp.into();
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        let prev_finish_timestamp = lot.finish_timestamp;
+        lot.extend(&seller_id, new_finish_timestamp, time_now);
+        self.internal_lot_save(&lot);
+
+        log!(
+            "lot_extend: {} finish_timestamp extended from {} to {}",
+            lot_id,
+            prev_finish_timestamp,
+            new_finish_timestamp
+        );
+
+        true
+    }
+
+    // Cancels the lot with bids. The top bidder gets the bid back plus lot_cancel_penalty,
+    // paid from the seller available rewards, fails if not enough. The seller claims the lot
+    // account back as for a withdrawn lot.
+    pub fn lot_cancel_with_penalty(&mut self, lot_id: LotId) -> bool {
+        let seller_id: ProfileId = env::predecessor_account_id();
+        let time_now = env::block_timestamp();
+        assert_eq!(
+            env::attached_deposit(),
+            0,
+            "{}",
+            ERR_LOT_CANCEL_WITH_PENALTY_DEPOSIT
+        );
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        let bid = lot.cancel(&seller_id, time_now);
+        self.internal_lot_save(&lot);
+
+        let penalty = self.lot_cancel_penalty * bid.amount;
+        let compensation = bid.amount + penalty;
+        let rewards_available = self.internal_profile_get(&seller_id).rewards_available();
+        assert!(
+            rewards_available >= compensation,
+            "{}",
+            ERR_LOT_CANCEL_WITH_PENALTY_NOT_ENOUGH_REWARDS,
+        );
+        self.internal_profile_rewards_withdraw(&seller_id, compensation);
+        self.internal_profile_rewards_transfer(&bid.bidder_id, compensation);
+
+        log!(
+            "lot_cancel_with_penalty: {} cancelled by {}, {} paid to {}",
+            lot_id,
+            seller_id,
+            compensation,
+            bid.bidder_id
+        );
+
+        true
+    }
+
+    // The winner relisting a lot with long bid history gets it relisted by lot_clean_up_step
+    // once the bids of the previous sale are removed
+    pub fn lot_reoffer(
+        &mut self,
+        lot_id: LotId,
+        reserve_price: WrappedBalance,
         buy_now_price: WrappedBalance,
         finish_timestamp: Option<WrappedTimestamp>,
         duration: Option<WrappedDuration>,
+        prev_bidder_commission_share: Option<FractionView>,
     ) -> bool {
-        let lot = self.internal_lot_extract(&lot_id);
+        let storage_usage = env::storage_usage();
+        let mut lot = self.internal_lot_extract(&lot_id);
         let caller_id: ProfileId = env::predecessor_account_id();
-        lot.validate_reoffer(&caller_id);
+        let start_timestamp: Timestamp = env::block_timestamp();
+        lot.validate_reoffer(&caller_id, start_timestamp);
 
         let reserve_price: Balance = reserve_price.into();
         let buy_now_price: Balance = buy_now_price.into();
-        let start_timestamp: Timestamp = env::block_timestamp();
         let finish_timestamp = Self::calc_finish_timestamp(
             start_timestamp,
             finish_timestamp.map(|x| x.into()),
             duration.map(|x| x.0),
         );
+        let prev_bidder_commission_share =
+            self.internal_validate_prev_bidder_commission_share(prev_bidder_commission_share);
+
+        if caller_id == lot.seller_id {
+            // lot without bids is relisted in place, keeping seller parameters,
+            // relist policy is reset as other auction parameters, raffles are rejected
+            let payouts = lot.payouts.clone();
+            let prev_share = prev_bidder_commission_share.or(lot.prev_bidder_commission_share);
+            let bid_step = (lot.bid_step, lot.bid_step_min_increment);
+            self.internal_lot_relist(
+                lot,
+                &caller_id,
+                reserve_price,
+                buy_now_price,
+                start_timestamp,
+                finish_timestamp,
+                payouts,
+                prev_share,
+                bid_step,
+            );
+            self.internal_storage_charge_lot(&lot_id, storage_usage);
 
-        // lot removed, internal_lot_offer will insert updated lot back
-        // skipping seller.lots_offering update
+            return true;
+        }
 
-        self.internal_lot_offer(
-            &lot.lot_id,
-            &lot.seller_id,
+        // winner relists the lot, payouts, reward share and bid step belong to the previous
+        // seller and are reset. Bids and associations of the previous sale are removed first
+        Lot::validate_new(
+            &lot_id,
+            &caller_id,
             reserve_price,
             buy_now_price,
             start_timestamp,
             finish_timestamp,
         );
+        log!(
+            "lot_reoffer: {} relisted by winner {}, previous seller {}",
+            lot_id,
+            caller_id,
+            lot.seller_id
+        );
+
+        let mut seller = self.internal_profile_extract(&lot.seller_id);
+        seller.lots_offering.remove(&lot.lot_id);
+        self.internal_profile_save(&seller);
+
+        if let Some(claimer_id) = lot.claimer_id_override.as_ref() {
+            let mut claimer = self.internal_profile_extract(claimer_id);
+            claimer.lots_bidding.remove(&lot.lot_id);
+            self.internal_profile_save(&claimer);
+        }
+
+        let prev_seller_id = lot.seller_id.clone();
+        lot.relist_pending = Some(LotRelist {
+            seller_id: caller_id,
+            reserve_price,
+            buy_now_price,
+            duration: finish_timestamp - start_timestamp,
+            prev_bidder_commission_share,
+        });
+        self.internal_storage_transfer_lot(&lot, &prev_seller_id);
+        self.internal_lot_save(&lot);
+        self.internal_storage_charge_lot(&lot_id, storage_usage);
+
+        let lot = self.internal_lot_extract(&lot_id);
+        self.internal_lot_clean_up_step(lot, LOT_CLEAN_UP_STEP_MAX_ITEMS);
 
         true
     }
@@ -390,69 +1395,268 @@ impl Contract {
             ERR_LOT_REMOVE_UNSAFE_LOT_ON_GRACE_PERIOD,
         );
 
+        assert!(!lot.is_cleaning_up, "{}", ERR_LOT_REMOVE_UNSAFE_CLEANING_UP,);
         assert!(
             lot.last_bid().is_none(),
             "{}",
             ERR_LOT_REMOVE_UNSAFE_LOT_HAS_BIDS,
         );
 
-        ext_lock_contract::get_owner(lot_id.clone(), NO_DEPOSIT, GAS_EXT_CALL_GET_OWNER.into())
-            .then(ext_self_contract::lot_after_remove_unsafe_remove(
+        // bundled accounts are checked too, the lot cannot be removed while any of them is locked
+        lock_infos_get(&lot.account_ids_locked()).then(
+            ext_self_contract::lot_after_remove_unsafe_remove(
                 lot_id,
                 env::current_account_id(),
                 NO_DEPOSIT,
                 GAS_EXT_CALL_AFTER_REMOVE_UNSAFE.into(),
-            ))
+            ),
+        )
     }
 
+    // The lot is removed only if none of its locked accounts is owned by the marketplace, so
+    // no account is left locked without a lot. Falls back to get_owner for the accounts which
+    // lock contract doesn't have get_lock_info
     #[private]
-    pub fn lot_after_remove_unsafe_remove(&mut self, lot_id: LotId) -> bool {
-        let is_safe: bool = match env::promise_result(0) {
-            PromiseResult::Successful(x) => {
-                let parse_result: Result<AccountId, _> = serde_json::from_slice(&x);
-                match parse_result {
-                    Ok(owner_id) => {
-                        if owner_id == env::current_account_id() {
-                            log!("lot_after_remove_unsafe_remove: seems safe");
-                            true
-                        } else {
-                            log!("lot_after_remove_unsafe_remove: wrong owner_id");
-                            false
-                        }
-                    }
-                    _ => {
-                        log!("lot_after_remove_unsafe_remove: owner parse failed");
-                        false
-                    }
-                }
-            }
-            _ => {
-                log!("lot_after_remove_unsafe_remove: promise_unsuccessful");
-                false
-            }
-        };
-        assert!(!is_safe, "{}", ERR_LOT_REMOVE_UNSAFE_LOT_SEEMS_SAFE);
+    pub fn lot_after_remove_unsafe_remove(&mut self, lot_id: LotId) -> PromiseOrValue<bool> {
+        let lot: Lot = self.lots.get(&lot_id).unwrap();
+        let account_ids = lot.account_ids_locked();
+        if account_ids.len() as u64 != env::promise_results_count() {
+            log!("lot_after_remove_unsafe_remove: bundle changed, lot kept");
+            return PromiseOrValue::Value(false);
+        }
 
-        let mut lot: Lot = self.internal_lot_extract(&lot_id);
-        assert!(lot.last_bid().is_none());
+        let lock_states = lock_states_from_lock_infos();
+        if lock_states.iter().all(|x| *x == LockState::Owned) {
+            log!("lot_after_remove_unsafe_remove: seems safe");
+            panic!("{}", ERR_LOT_REMOVE_UNSAFE_LOT_SEEMS_SAFE);
+        }
+        if lock_states.contains(&LockState::Owned) {
+            log!("lot_after_remove_unsafe_remove: some accounts still locked, lot kept");
+            return PromiseOrValue::Value(false);
+        }
+        if lock_states.contains(&LockState::Unknown) {
+            log!("lot_after_remove_unsafe_remove: lock info parse failed, lot kept");
+            return PromiseOrValue::Value(false);
+        }
 
-        {
-            let mut seller = self.internal_profile_extract(&lot.seller_id);
-            seller.lots_offering.remove(&lot_id);
-            self.internal_profile_save(&seller);
+        let account_ids_failed: Vec<LotId> = account_ids
+            .into_iter()
+            .zip(lock_states)
+            .filter(|(_, lock_state)| *lock_state == LockState::Failed)
+            .map(|(account_id, _)| account_id)
+            .collect();
+        if !account_ids_failed.is_empty() {
+            log!(
+                "lot_after_remove_unsafe_remove: lock info unavailable, checking owner of {:?}",
+                account_ids_failed
+            );
+            return PromiseOrValue::Promise(owners_get(&account_ids_failed).then(
+                ext_self_contract::lot_after_remove_unsafe_get_owner(
+                    lot_id,
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_EXT_CALL_AFTER_REMOVE_UNSAFE_GET_OWNER.into(),
+                ),
+            ));
         }
 
-        lot.clean_up();
+        log!("lot_after_remove_unsafe_remove: wrong owner_id");
+        PromiseOrValue::Value(self.internal_lot_remove_unsafe(&lot_id))
+    }
 
-        // intentionally not inserting the lot back
-        true
+    // Fallback for the lock contracts without get_lock_info. If get_owner is missing too,
+    // there is no lock contract on the account at all
+    #[private]
+    pub fn lot_after_remove_unsafe_get_owner(&mut self, lot_id: LotId) -> bool {
+        let lot: Lot = self.lots.get(&lot_id).unwrap();
+        let lock_states = lock_states_from_owners();
+        if lock_states.contains(&LockState::Unknown) {
+            log!("lot_after_remove_unsafe_remove: owner parse failed, lot kept");
+            return false;
+        }
+        if lock_states.contains(&LockState::Owned) {
+            // the rest of the accounts are checked to be not owned before the fallback
+            let is_safe = lock_states.len() == lot.account_ids_locked().len()
+                && lock_states.iter().all(|x| *x == LockState::Owned);
+            assert!(!is_safe, "{}", ERR_LOT_REMOVE_UNSAFE_LOT_SEEMS_SAFE);
+            log!("lot_after_remove_unsafe_remove: some accounts still locked, lot kept");
+            return false;
+        }
+
+        if lock_states.iter().all(|x| *x == LockState::Failed) {
+            log!("lot_after_remove_unsafe_remove: promise_unsuccessful");
+        } else {
+            log!("lot_after_remove_unsafe_remove: wrong owner_id");
+        }
+        self.internal_lot_remove_unsafe(&lot_id)
+    }
+
+    // Cleans up lot which accounts are not owned by marketplace anymore, e.g. if
+    // lot_after_claim_clean_up failed after successful unlock. If any account is still owned,
+    // reverts the pending claim stuck for longer than the grace, so the lot can be claimed again.
+    pub fn lot_reconcile(&mut self, lot_id: LotId) -> Promise {
+        let lot: Lot = self.lots.get(&lot_id).unwrap();
+        let time_now = env::block_timestamp();
+        assert!(
+            !lot.is_active(time_now),
+            "{}",
+            ERR_LOT_RECONCILE_STILL_ACTIVE,
+        );
+        assert!(!lot.is_cleaning_up, "{}", ERR_LOT_RECONCILE_CLEANING_UP,);
+
+        lock_infos_get(&lot.account_ids_locked()).then(ext_self_contract::lot_after_reconcile(
+            lot_id,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_EXT_CALL_AFTER_RECONCILE.into(),
+        ))
+    }
+
+    // Falls back to get_owner for the accounts which lock contract doesn't have get_lock_info
+    #[private]
+    pub fn lot_after_reconcile(&mut self, lot_id: LotId) -> PromiseOrValue<bool> {
+        let lot: Lot = self.lots.get(&lot_id).unwrap();
+        let account_ids = lot.account_ids_locked();
+        if account_ids.len() as u64 != env::promise_results_count() {
+            log!("lot_after_reconcile: bundle changed, lot kept");
+            return PromiseOrValue::Value(false);
+        }
+
+        // the lot is kept as is if the lock state of any account is unknown
+        let lock_states = lock_states_from_lock_infos();
+        if lock_states.contains(&LockState::Unknown) {
+            log!("lot_after_reconcile: lock state unknown, lot kept");
+            return PromiseOrValue::Value(false);
+        }
+
+        let is_owned = lock_states.contains(&LockState::Owned);
+        let account_ids_failed: Vec<LotId> = account_ids
+            .into_iter()
+            .zip(lock_states)
+            .filter(|(_, lock_state)| *lock_state == LockState::Failed)
+            .map(|(account_id, _)| account_id)
+            .collect();
+        if !account_ids_failed.is_empty() {
+            log!(
+                "lot_after_reconcile: lock info unavailable, checking owner of {:?}",
+                account_ids_failed
+            );
+            return PromiseOrValue::Promise(owners_get(&account_ids_failed).then(
+                ext_self_contract::lot_after_reconcile_get_owner(
+                    lot_id,
+                    is_owned,
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_EXT_CALL_AFTER_RECONCILE_GET_OWNER.into(),
+                ),
+            ));
+        }
+
+        PromiseOrValue::Value(self.internal_lot_reconcile(
+            &lot_id,
+            is_owned,
+            LOT_CLEAN_UP_STEP_MAX_ITEMS,
+        ))
+    }
+
+    // Fallback for the lock contracts without get_lock_info, is_owned tells if any of the other
+    // accounts is still owned. If get_owner is missing too, there is no lock contract at all
+    #[private]
+    pub fn lot_after_reconcile_get_owner(&mut self, lot_id: LotId, is_owned: bool) -> bool {
+        let lock_states = lock_states_from_owners();
+        if lock_states.contains(&LockState::Unknown) {
+            log!("lot_after_reconcile: owner parse failed, lot kept");
+            return false;
+        }
+
+        let is_owned = is_owned || lock_states.contains(&LockState::Owned);
+        self.internal_lot_reconcile(&lot_id, is_owned, LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY)
     }
 }
 
+// Ownership of a lot account by the marketplace as reported by its lock contract
+#[derive(PartialEq)]
+enum LockState {
+    Owned,
+    NotOwned,
+    // unexpected response of the lock contract
+    Unknown,
+    // the call failed, e.g. the lock contract doesn't have the method
+    Failed,
+}
+
+// Queries get_lock_info of the accounts in the given order, the results are read with
+// lock_states_from_lock_infos
+fn lock_infos_get(account_ids: &[LotId]) -> Promise {
+    let (first_id, other_ids) = account_ids.split_first().unwrap();
+    other_ids
+        .iter()
+        .map(|account_id| {
+            ext_lock_contract::get_lock_info(
+                account_id.clone(),
+                NO_DEPOSIT,
+                GAS_EXT_CALL_GET_LOCK_INFO_BUNDLED.into(),
+            )
+        })
+        .fold(
+            ext_lock_contract::get_lock_info(
+                first_id.clone(),
+                NO_DEPOSIT,
+                GAS_EXT_CALL_GET_LOCK_INFO.into(),
+            ),
+            |acc, lock_info| acc.and(lock_info),
+        )
+}
+
+// Queries get_owner of the accounts in the given order, the results are read with
+// lock_states_from_owners
+fn owners_get(account_ids: &[LotId]) -> Promise {
+    let (first_id, other_ids) = account_ids.split_first().unwrap();
+    other_ids.iter().fold(
+        ext_lock_contract::get_owner(first_id.clone(), NO_DEPOSIT, GAS_EXT_CALL_GET_OWNER.into()),
+        |acc, account_id| {
+            acc.and(ext_lock_contract::get_owner(
+                account_id.clone(),
+                NO_DEPOSIT,
+                GAS_EXT_CALL_GET_OWNER.into(),
+            ))
+        },
+    )
+}
+
+fn lock_states_from_lock_infos() -> Vec<LockState> {
+    (0..env::promise_results_count())
+        .map(|index| match env::promise_result(index) {
+            PromiseResult::Successful(x) => match serde_json::from_slice::<LockInfoView>(&x) {
+                Ok(x) if !x.is_unlocked && x.owner_id == env::current_account_id() => {
+                    LockState::Owned
+                }
+                Ok(_) => LockState::NotOwned,
+                _ => LockState::Unknown,
+            },
+            _ => LockState::Failed,
+        })
+        .collect()
+}
+
+fn lock_states_from_owners() -> Vec<LockState> {
+    (0..env::promise_results_count())
+        .map(|index| match env::promise_result(index) {
+            PromiseResult::Successful(x) => match serde_json::from_slice::<AccountId>(&x) {
+                Ok(owner_id) if owner_id == env::current_account_id() => LockState::Owned,
+                Ok(_) => LockState::NotOwned,
+                _ => LockState::Unknown,
+            },
+            _ => LockState::Failed,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::tests::*;
 
+    use crate::api_storage::tests::*;
     use crate::lot::tests::*;
 
     pub fn create_lot_x_sells_y_api(
@@ -465,6 +1669,7 @@ pub mod tests {
         let start_timestamp = to_ts(10);
         let finish_timestamp = to_ts(17);
 
+        api_storage_deposit(contract, seller_id);
         testing_env!(get_context_call(start_timestamp, lot_id));
         contract.lot_offer(
             seller_id.clone(),
@@ -472,14 +1677,26 @@ pub mod tests {
             buy_now_price.into(),
             Some(WrappedTimestamp::from(finish_timestamp)),
             None,
-        );
-
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
         contract.lots.get(&lot_id).unwrap()
     }
 
     pub fn api_lot_bid(contract: &mut Contract, lot_id: &LotId, bid: &Bid) {
+        api_storage_deposit(contract, &bid.bidder_id);
         testing_env!(get_context_pay(bid.timestamp, &bid.bidder_id, bid.amount));
-        contract.lot_bid(lot_id.clone());
+        contract.lot_bid(
+            lot_id.clone(),
+            bid.public_key.clone(),
+            bid.referrer_id.clone(),
+            bid.beneficiary_id.clone(),
+        );
     }
 
     #[test]
@@ -539,11 +1756,13 @@ pub mod tests {
         let expected: Vec<BidView> = vec![
             BidView {
                 bidder_id: "carol".parse().unwrap(),
+                beneficiary_id: None,
                 amount: WrappedBalance::from(to_yocto("3")),
                 timestamp: WrappedTimestamp::from(to_ts(11)),
             },
             BidView {
                 bidder_id: "dan".parse().unwrap(),
+                beneficiary_id: None,
                 amount: WrappedBalance::from(to_yocto("6")),
                 timestamp: WrappedTimestamp::from(to_ts(12)),
             },
@@ -559,6 +1778,34 @@ pub mod tests {
         assert_eq!(response[1].timestamp, expected[1].timestamp);
     }
 
+    #[test]
+    fn test_api_lot_bid_list_beneficiary() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+        let bid = Bid {
+            bidder_id: "carol".parse().unwrap(),
+            amount: to_yocto("3"),
+            timestamp: to_ts(11),
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: Some("eve".parse().unwrap()),
+            rewards: BidRewards::default(),
+            storage_usage: 0,
+        };
+        api_lot_bid(&mut contract, &"alice".parse().unwrap(), &bid);
+
+        testing_env!(get_context_view(to_ts(11)));
+        let response: Vec<BidView> = contract.lot_bid_list("alice".parse().unwrap());
+        assert_eq!(response.len(), 1, "wrong bids length");
+        assert_eq!(response[0].bidder_id, "carol".parse().unwrap());
+        assert_eq!(
+            response[0].beneficiary_id,
+            Some("eve".parse().unwrap()),
+            "expected beneficiary in bid view",
+        );
+    }
+
     #[test]
     fn test_api_lot_list_empty() {
         let contract = build_contract();
@@ -750,6 +1997,11 @@ pub mod tests {
                     bidder_id: bidder_id.clone(),
                     amount: to_yocto("6"),
                     timestamp: to_ts(11),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
                 },
             );
         }
@@ -800,6 +2052,7 @@ pub mod tests {
         let start_timestamp = to_ts(10);
         let finish_timestamp = to_ts(17);
 
+        api_storage_deposit(&mut contract, &seller_id);
         testing_env!(get_context_call(start_timestamp, &lot_id));
         contract.lot_offer(
             seller_id.clone(),
@@ -807,6 +2060,12 @@ pub mod tests {
             buy_now_price.into(),
             Some(WrappedTimestamp::from(finish_timestamp)),
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
 
         let result = contract.internal_lot_extract(&lot_id);
@@ -830,6 +2089,7 @@ pub mod tests {
         let duration = to_nanos(7);
         let time_now = to_ts(10);
 
+        api_storage_deposit(&mut contract, &seller_id);
         testing_env!(get_context_call(time_now, &lot_id));
         contract.lot_offer(
             seller_id.clone(),
@@ -837,6 +2097,12 @@ pub mod tests {
             buy_now_price.into(),
             None,
             Some(WrappedDuration::from(duration)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
 
         let result = contract.internal_lot_extract(&lot_id);
@@ -849,6 +2115,32 @@ pub mod tests {
         assert_eq!(result.buy_now_price, buy_now_price.into());
     }
 
+    #[test]
+    fn test_api_lot_offer_sweep_on_claim() {
+        let mut contract = build_contract();
+        let lot_id: LotId = "alice".parse().unwrap();
+
+        api_storage_deposit(&mut contract, &"bob".parse().unwrap());
+        testing_env!(get_context_call(to_ts(10), &lot_id));
+        contract.lot_offer(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            None,
+            Some(WrappedDuration::from(to_nanos(7))),
+            Some(true),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        testing_env!(get_context_view(to_ts(11)));
+        let result = contract.lot_get(lot_id.clone()).unwrap();
+        assert_eq!(result.sweep_on_claim, true, "expected sweep on claim");
+    }
+
     fn check_rewards(contract: &Contract, profile_id: &ProfileId) -> Balance {
         contract
             .internal_profile_get(profile_id)
@@ -879,6 +2171,11 @@ pub mod tests {
                 bidder_id: carol.clone(),
                 amount: first_bid_amount,
                 timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
             },
         );
 
@@ -899,6 +2196,11 @@ pub mod tests {
                 bidder_id: dan.clone(),
                 amount: second_bid_amount,
                 timestamp: to_ts(12),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
             },
         );
 
@@ -915,6 +2217,261 @@ pub mod tests {
         assert_eq!(check_rewards(&contract, &dan), to_yocto("0"));
     }
 
+    #[test]
+    pub fn test_api_lot_bid_referrer_rewards() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+
+        // commission 0.6, half of it to referrer
+        api_storage_deposit(&mut contract, &carol);
+        testing_env!(get_context_pay(to_ts(11), &carol, to_yocto("6")));
+        contract.lot_bid(alice.clone(), None, Some(eve.clone()), None);
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("5.4"));
+        assert_eq!(check_rewards(&contract, &eve), to_yocto("0.3"));
+
+        // commission 0.2, 0.16 to prev bidder, half of the rest to referrer
+        api_storage_deposit(&mut contract, &dan);
+        testing_env!(get_context_pay(to_ts(12), &dan, to_yocto("8")));
+        contract.lot_bid(alice.clone(), None, Some(eve.clone()), None);
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("7.2"));
+        assert_eq!(check_rewards(&contract, &carol), to_yocto("6.16"));
+        assert_eq!(check_rewards(&contract, &eve), to_yocto("0.32"));
+
+        let result = contract.profile_get(eve);
+        assert_eq!(
+            result.referral_rewards_total,
+            to_yocto("0.32").into(),
+            "wrong referral rewards total",
+        );
+        let result = contract.profile_get(bob);
+        assert_eq!(
+            result.referral_rewards_total,
+            0.into(),
+            "expected no referral rewards for seller",
+        );
+    }
+
+    fn create_lot_alice_with_referred_bids_api() -> Contract {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        for (bidder_id, amount, timestamp) in [("carol", "6", 11), ("dan", "8", 12)] {
+            api_lot_bid(
+                &mut contract,
+                &"alice".parse().unwrap(),
+                &Bid {
+                    bidder_id: bidder_id.parse().unwrap(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(timestamp),
+                    public_key: None,
+                    referrer_id: Some("eve".parse().unwrap()),
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+
+        contract
+    }
+
+    #[test]
+    pub fn test_api_lot_bid_retract() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let mut contract = create_lot_alice_with_referred_bids_api();
+
+        testing_env!(get_context_call(
+            to_ts(12) + LOT_BID_RETRACT_DURATION / 2,
+            &dan
+        ));
+        contract.lot_bid_retract(alice.clone());
+
+        // rewards for the second bid reversed, penalty 0.8 to seller
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("6.2"));
+        assert_eq!(check_rewards(&contract, &carol), 0);
+        assert_eq!(check_rewards(&contract, &dan), 0);
+        assert_eq!(check_rewards(&contract, &eve), to_yocto("0.3"));
+        assert_eq!(
+            contract.profile_get(eve.clone()).referral_rewards_total,
+            to_yocto("0.3").into(),
+            "wrong referral rewards total",
+        );
+
+        let lot = contract.lots.get(&alice).unwrap();
+        assert_eq!(lot.bids().len(), 1, "expected one bid left");
+        let last_bid = lot.last_bid().unwrap();
+        assert_eq!(last_bid.bidder_id, carol, "expected previous bid restored");
+        assert_eq!(last_bid.amount, to_yocto("6"), "wrong last bid amount");
+
+        assert!(
+            contract.lot_list_bidding_by(dan, None, None).is_empty(),
+            "expected lot removed from retracted bidder lots",
+        );
+        assert_eq!(contract.lot_list_bidding_by(carol, None, None).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "rewards withdraw: not enough rewards")]
+    pub fn test_api_lot_bid_retract_fail_rewards_claimed() {
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let mut contract = create_lot_alice_with_referred_bids_api();
+
+        testing_env!(get_context_call(to_ts(12) + 1, &carol));
+        contract.profile_rewards_claim();
+
+        testing_env!(get_context_call(
+            to_ts(12) + LOT_BID_RETRACT_DURATION / 2,
+            &"dan".parse().unwrap()
+        ));
+        contract.lot_bid_retract(alice);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid retract: expected last bidder")]
+    pub fn test_api_lot_bid_retract_fail_outbid() {
+        let mut contract = create_lot_alice_with_referred_bids_api();
+
+        testing_env!(get_context_call(
+            to_ts(12) + LOT_BID_RETRACT_DURATION / 2,
+            &"carol".parse().unwrap()
+        ));
+        contract.lot_bid_retract("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "bid retract: retraction window expired")]
+    pub fn test_api_lot_bid_retract_fail_too_late() {
+        let mut contract = create_lot_alice_with_referred_bids_api();
+
+        testing_env!(get_context_call(
+            to_ts(12) + LOT_BID_RETRACT_DURATION + 1,
+            &"dan".parse().unwrap()
+        ));
+        contract.lot_bid_retract("alice".parse().unwrap());
+    }
+
+    #[test]
+    pub fn test_api_lot_bid_retract_reverses_credited_rewards() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let mut contract = create_lot_alice_with_referred_bids_api();
+
+        // config changed between the bid and the retraction
+        contract.seller_rewards_commission = Fraction::new(1, 2);
+        contract.prev_bidder_commission_share = Fraction::new(0, 1);
+        contract.referrer_commission_share = Fraction::new(0, 1);
+
+        testing_env!(get_context_call(
+            to_ts(12) + LOT_BID_RETRACT_DURATION / 2,
+            &"dan".parse().unwrap()
+        ));
+        contract.lot_bid_retract(alice);
+
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("6.2"));
+        assert_eq!(check_rewards(&contract, &carol), 0);
+        assert_eq!(check_rewards(&contract, &eve), to_yocto("0.3"));
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_bid: bidder, seller and lot cannot refer")]
+    pub fn test_api_lot_bid_fail_self_referrer() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+        let carol: ProfileId = "carol".parse().unwrap();
+
+        api_storage_deposit(&mut contract, &carol);
+        testing_env!(get_context_pay(to_ts(11), &carol, to_yocto("6")));
+        contract.lot_bid("alice".parse().unwrap(), None, Some(carol), None);
+    }
+
+    #[test]
+    pub fn test_api_lot_bid_with_beneficiary() {
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        api_storage_deposit(&mut contract, &carol);
+        testing_env!(get_context_pay(to_ts(11), &carol, to_yocto("6")));
+        contract.lot_bid(alice.clone(), None, None, Some(eve.clone()));
+
+        let lot = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(lot.last_bidder_id, Some(carol.clone()));
+        assert_eq!(
+            lot.claimer_id,
+            Some(eve.clone()),
+            "expected beneficiary claimer"
+        );
+        let bids = contract.lot_bid_list(alice.clone());
+        assert_eq!(bids[0].bidder_id, carol);
+        assert_eq!(bids[0].beneficiary_id, Some(eve.clone()));
+        assert_eq!(
+            contract
+                .lot_list_bidding_by(carol.clone(), None, None)
+                .len(),
+            1
+        );
+        assert_eq!(
+            contract.lot_list_bidding_by(eve.clone(), None, None).len(),
+            1
+        );
+
+        // outbid reward goes to the payer
+        api_storage_deposit(&mut contract, &dan);
+        testing_env!(get_context_pay(to_ts(12), &dan, to_yocto("8")));
+        contract.lot_bid(alice.clone(), None, None, None);
+        assert_eq!(check_rewards(&contract, &carol), to_yocto("6.16"));
+        assert_eq!(check_rewards(&contract, &eve), 0);
+    }
+
+    #[test]
+    pub fn test_api_lot_claim_by_beneficiary() {
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        api_storage_deposit(&mut contract, &carol);
+        testing_env!(get_context_pay(to_ts(11), &carol, to_yocto("10")));
+        contract.lot_bid(alice.clone(), None, None, Some(eve.clone()));
+
+        testing_env!(get_context_call(to_ts(12), &eve));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+        contract.lot_claim(alice.clone(), public_key, None);
+
+        set_context_callback(to_ts(12), vec![PromiseResult::Successful(vec![])]);
+        contract.lot_after_claim_clean_up(alice, LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert!(
+            contract.lot_list_bidding_by(carol, None, None).is_empty(),
+            "expected bidder association removed",
+        );
+        assert!(
+            contract.lot_list_bidding_by(eve, None, None).is_empty(),
+            "expected beneficiary association removed",
+        );
+    }
+
     #[test]
     pub fn test_api_lot_list_bidding_by_offering_by_fields() {
         let alice: LotId = "alice".parse().unwrap();
@@ -931,6 +2488,11 @@ pub mod tests {
                 bidder_id: carol.clone(),
                 amount: to_yocto("6"),
                 timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
             },
         );
 
@@ -968,217 +2530,2006 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_api_lot_get_present() {
+    pub fn test_api_lot_get_present() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_view(time_now));
+        let alice: LotId = "alice".parse().unwrap();
+        let result = contract.lot_get(alice.clone());
+        assert!(result.is_some(), "expected: lot present");
+        let result = result.unwrap();
+        assert_eq!(result.lot_id, alice.clone());
+    }
+
+    #[test]
+    pub fn test_api_lot_get_not_present() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_view(time_now));
+        let bob: LotId = "bob".parse().unwrap();
+        let result = contract.lot_get(bob.clone());
+        assert!(result.is_none(), "expected: lot not present");
+    }
+
+    #[test]
+    #[should_panic(expected = "bid: expected bigger bid")]
+    pub fn test_api_lot_bid_fail_small_bid() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        api_lot_bid(
+            &mut contract,
+            &"alice".parse().unwrap(),
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("1"),
+                timestamp: time_now,
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bid: expected status active")]
+    pub fn test_api_lot_bid_fail_inactive() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_sale_success();
+        contract.internal_lot_save(&lot);
+
+        api_lot_bid(
+            &mut contract,
+            &"alice".parse().unwrap(),
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("10"),
+                timestamp: time_now,
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+    }
+
+    const NEW_PUBLIC_KEY: &str = "ed25519:KEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYK";
+
+    #[test]
+    pub fn test_api_lot_claim_success() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+    }
+
+    #[test]
+    pub fn test_api_lot_claim_success_by_seller_withdrawn() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_withdrawn();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+    }
+
+    #[test]
+    pub fn test_api_lot_claim_success_claim_pending() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "ClaimPending", "expected claim pending");
+    }
+
+    fn create_lots_alice_frank_sold_to_carol(contract: &mut Contract) -> Timestamp {
+        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        contract.internal_lot_save(&lot);
+        let mut lot = create_lot_x_sells_y(&"bob".parse().unwrap(), &"frank".parse().unwrap());
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("10"),
+                timestamp: to_ts(13),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        contract.internal_lot_save(&lot);
+
+        time_now
+    }
+
+    #[test]
+    pub fn test_api_lot_claim_many_success() {
+        let mut contract = build_contract();
+        let time_now = create_lots_alice_frank_sold_to_carol(&mut contract);
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        let result = contract.lot_claim_many(vec![
+            ("alice".parse().unwrap(), public_key.clone()),
+            ("frank".parse().unwrap(), public_key),
+        ]);
+        assert_eq!(result, true);
+
+        for lot_id in ["alice", "frank"] {
+            let result = contract.lot_get(lot_id.parse().unwrap()).unwrap();
+            assert_eq!(result.status, "ClaimPending", "expected claim pending");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "claim by bidder: wrong claimer")]
+    pub fn test_api_lot_claim_many_fail_one_lot_invalid() {
+        let mut contract = build_contract();
+        let time_now = create_lots_alice_frank_sold_to_carol(&mut contract);
+        let mut lot = create_lot_x_sells_y(&"bob".parse().unwrap(), &"gina".parse().unwrap());
+        lot.place_bid(
+            &Bid {
+                bidder_id: "dan".parse().unwrap(),
+                amount: to_yocto("10"),
+                timestamp: to_ts(13),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim_many(vec![
+            ("alice".parse().unwrap(), public_key.clone()),
+            ("gina".parse().unwrap(), public_key.clone()),
+            ("frank".parse().unwrap(), public_key),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "claim: already pending")]
+    pub fn test_api_lot_claim_many_fail_duplicate() {
+        let mut contract = build_contract();
+        let time_now = create_lots_alice_frank_sold_to_carol(&mut contract);
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim_many(vec![
+            ("alice".parse().unwrap(), public_key.clone()),
+            ("alice".parse().unwrap(), public_key),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_claim_many: not enough gas attached")]
+    pub fn test_api_lot_claim_many_fail_not_enough_gas() {
+        let mut contract = build_contract();
+        let time_now = create_lots_alice_frank_sold_to_carol(&mut contract);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id("carol".parse().unwrap())
+            .block_timestamp(time_now)
+            .prepaid_gas(100_000_000_000_000.into())
+            .build());
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim_many(vec![
+            ("alice".parse().unwrap(), public_key.clone()),
+            ("frank".parse().unwrap(), public_key),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_claim_many: expected lots to claim")]
+    pub fn test_api_lot_claim_many_fail_empty() {
+        let mut contract = build_contract();
+        testing_env!(get_context_call(to_ts(18), &"carol".parse().unwrap()));
+
+        contract.lot_claim_many(vec![]);
+    }
+
+    #[test]
+    pub fn test_api_lot_finalize_success() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_public_key_sale_success();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"eve".parse().unwrap()));
+
+        contract.lot_finalize("alice".parse().unwrap());
+
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "ClaimPending", "expected claim pending");
+    }
+
+    #[test]
+    #[should_panic(expected = "finalize: winning bid has no public key")]
+    pub fn test_api_lot_finalize_fail_no_public_key() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"eve".parse().unwrap()));
+
+        contract.lot_finalize("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "claim: already pending")]
+    pub fn test_api_lot_claim_fail_claim_pending() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim("alice".parse().unwrap(), public_key.clone(), None);
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+    }
+
+    #[test]
+    pub fn test_api_lot_after_claim_clean_up_unlock_failed() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
+        contract.internal_lot_save(&lot);
+
+        set_context_callback(time_now, vec![PromiseResult::Failed]);
+        let result = contract
+            .lot_after_claim_clean_up("alice".parse().unwrap(), LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert_eq!(result, false, "expected clean up to fail");
+
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "SaleSuccess", "expected claim reverted");
+    }
+
+    #[test]
+    pub fn test_api_lot_after_claim_sweep() {
+        let mut contract = build_contract();
+        set_context_callback(
+            to_ts(12),
+            vec![PromiseResult::Successful(b"\"5000\"".to_vec())],
+        );
+        let result =
+            contract.lot_after_claim_sweep("alice".parse().unwrap(), "bob".parse().unwrap());
+        assert_eq!(result.0, 5000, "expected swept amount returned");
+
+        set_context_callback(to_ts(12), vec![PromiseResult::Failed]);
+        let result =
+            contract.lot_after_claim_sweep("alice".parse().unwrap(), "bob".parse().unwrap());
+        assert_eq!(result.0, 0, "expected nothing swept on failure");
+    }
+
+    #[test]
+    pub fn test_api_lot_after_claim_clean_up_key_not_added() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
+        contract.internal_lot_save(&lot);
+
+        set_context_callback(time_now, vec![PromiseResult::Successful(b"false".to_vec())]);
+        let result = contract
+            .lot_after_claim_clean_up("alice".parse().unwrap(), LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert_eq!(result, false, "expected clean up to fail");
+
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "SaleSuccess", "expected claim reverted");
+    }
+
+    #[test]
+    pub fn test_api_lot_after_claim_clean_up_success() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
+        contract.internal_lot_save(&lot);
+
+        set_context_callback(time_now, vec![PromiseResult::Successful(vec![])]);
+        let result = contract
+            .lot_after_claim_clean_up("alice".parse().unwrap(), LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert_eq!(result, true, "expected clean up to succeed");
+        assert!(
+            contract.lot_get("alice".parse().unwrap()).is_none(),
+            "expected lot removed"
+        );
+    }
+
+    fn api_lot_bundle_join(contract: &mut Contract, lot_id: &LotId, account_id: &LotId) -> bool {
+        testing_env!(get_context_call(to_ts(11), account_id));
+        contract.lot_bundle_join(lot_id.clone());
+
+        set_context_callback(to_ts(11), vec![lock_info_result("marketplace", false)]);
+        contract.lot_after_bundle_join(lot_id.clone(), account_id.clone())
+    }
+
+    fn create_lot_alice_bundle_api(contract: &mut Contract) {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        create_lot_x_sells_y_api(contract, &bob, &alice);
+        for account_id in ["alice-app", "alice-dao"] {
+            api_lot_bundle_join(contract, &alice, &account_id.parse().unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_api_lot_bundle_join() {
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+
+        let lot = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(
+            lot.bundle_ids,
+            vec![
+                "alice-app".parse::<LotId>().unwrap(),
+                "alice-dao".parse::<LotId>().unwrap()
+            ],
+            "wrong bundle ids",
+        );
+        assert_eq!(
+            contract
+                .lot_bundle_members
+                .get(&"alice-app".parse().unwrap()),
+            Some("alice".parse().unwrap()),
+            "expected bundle member",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_offer: account is bundled with another lot")]
+    pub fn test_api_lot_offer_fail_bundled() {
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+        create_lot_x_sells_y_api(
+            &mut contract,
+            &"bob".parse().unwrap(),
+            &"alice-app".parse().unwrap(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_bundle_join: account is offered as a lot")]
+    pub fn test_api_lot_bundle_join_fail_lot_exists() {
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+        create_lot_x_sells_y_api(
+            &mut contract,
+            &"bob".parse().unwrap(),
+            &"frank".parse().unwrap(),
+        );
+
+        testing_env!(get_context_call(to_ts(11), &"frank".parse().unwrap()));
+        contract.lot_bundle_join("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_bundle_join: account is already bundled")]
+    pub fn test_api_lot_bundle_join_fail_already_bundled() {
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+        create_lot_x_sells_y_api(
+            &mut contract,
+            &"bob".parse().unwrap(),
+            &"frank".parse().unwrap(),
+        );
+
+        testing_env!(get_context_call(to_ts(11), &"alice-app".parse().unwrap()));
+        contract.lot_bundle_join("frank".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "bundle join: seller and lot cannot join")]
+    pub fn test_api_lot_bundle_join_fail_seller() {
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+
+        testing_env!(get_context_call(to_ts(11), &"bob".parse().unwrap()));
+        contract.lot_bundle_join("alice".parse().unwrap());
+    }
+
+    #[test]
+    pub fn test_api_lot_after_bundle_join_not_locked() {
+        let alice: LotId = "alice".parse().unwrap();
+        let alice_app: LotId = "alice-app".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_x_sells_y_api(&mut contract, &"bob".parse().unwrap(), &alice);
+
+        for promise_result in [
+            lock_info_result("bob", false),
+            lock_info_result("alice-app", true),
+            PromiseResult::Failed,
+        ] {
+            set_context_callback(to_ts(11), vec![promise_result]);
+            let result = contract.lot_after_bundle_join(alice.clone(), alice_app.clone());
+            assert!(!result, "expected account rejected");
+        }
+
+        let lot = contract.lot_get(alice).unwrap();
+        assert!(lot.bundle_ids.is_empty(), "expected no bundle");
+        assert!(contract.lot_bundle_members.get(&alice_app).is_none());
+    }
+
+    #[test]
+    pub fn test_api_lot_after_remove_unsafe_remove_bundle() {
+        let alice: LotId = "alice".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+
+        let time_now = to_ts(10) + LOT_REMOVE_UNSAFE_GRACE_DURATION;
+        set_context_callback(
+            time_now,
+            vec![
+                lock_info_result("marketplace", false),
+                lock_info_result("marketplace", false),
+            ],
+        );
+        let result = contract.lot_after_remove_unsafe_remove(alice.clone());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected kept on results mismatch"
+        );
+        assert!(contract.lot_get(alice.clone()).is_some());
+
+        set_context_callback(
+            time_now,
+            vec![
+                lock_info_result("marketplace", false),
+                lock_info_result("marketplace", false),
+                lock_info_result("bob", true),
+            ],
+        );
+        let result = contract.lot_after_remove_unsafe_remove(alice.clone());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected kept while bundled accounts are locked"
+        );
+        assert!(contract.lot_get(alice.clone()).is_some());
+
+        set_context_callback(
+            time_now,
+            vec![
+                lock_info_result("alice", true),
+                lock_info_result("bob", false),
+                PromiseResult::Failed,
+            ],
+        );
+        let result = contract.lot_after_remove_unsafe_remove(alice.clone());
+        assert!(
+            matches!(result, PromiseOrValue::Promise(_)),
+            "expected fallback for the failed account"
+        );
+
+        set_context_callback(time_now, vec![PromiseResult::Failed]);
+        let result = contract.lot_after_remove_unsafe_get_owner(alice.clone());
+        assert_eq!(result, true, "expected removed with no account locked");
+        assert!(contract.lot_get(alice).is_none());
+    }
+
+    #[test]
+    pub fn test_api_lot_after_reconcile_bundle_member_still_locked() {
+        let alice: LotId = "alice".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("3"),
+                timestamp: to_ts(12),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+        let time_now = to_ts(18);
+
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_claim(alice.clone(), NEW_PUBLIC_KEY.parse().unwrap(), None);
+
+        set_context_callback(
+            time_now,
+            vec![
+                PromiseResult::Successful(vec![]),
+                PromiseResult::Failed,
+                PromiseResult::Successful(vec![]),
+            ],
+        );
+        contract.lot_after_claim_clean_up(alice.clone(), LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY);
+
+        // only alice-app is queried, the lot account is already unlocked
+        testing_env!(get_context_call(time_now, &"dan".parse().unwrap()));
+        contract.lot_reconcile(alice.clone());
+
+        set_context_callback(time_now, vec![lock_info_result("marketplace", false)]);
+        let result = contract.lot_after_reconcile(alice.clone());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected lot kept while alice-app is locked"
+        );
+
+        let lot = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(lot.status, "SaleSuccess", "expected lot claimable again");
+        assert_eq!(
+            contract
+                .lot_bundle_members
+                .get(&"alice-app".parse().unwrap()),
+            Some(alice),
+            "expected bundle member kept",
+        );
+    }
+
+    #[test]
+    pub fn test_api_lot_bundle_claim_retry_failed_unlock() {
+        let mut contract = build_contract();
+        create_lot_alice_bundle_api(&mut contract);
+        api_lot_bid(
+            &mut contract,
+            &"alice".parse().unwrap(),
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("3"),
+                timestamp: to_ts(12),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+        let time_now = to_ts(18);
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_claim("alice".parse().unwrap(), public_key.clone(), None);
+
+        set_context_callback(
+            time_now,
+            vec![
+                PromiseResult::Successful(vec![]),
+                PromiseResult::Failed,
+                PromiseResult::Successful(vec![]),
+            ],
+        );
+        let result = contract
+            .lot_after_claim_clean_up("alice".parse().unwrap(), LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY);
+        assert_eq!(result, false, "expected clean up to wait for failed unlock");
+
+        let lot = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(lot.status, "SaleSuccess", "expected claim reverted");
+        assert_eq!(
+            lot.unlocked_ids,
+            vec![
+                "alice".parse::<LotId>().unwrap(),
+                "alice-dao".parse::<LotId>().unwrap()
+            ],
+            "wrong unlocked ids",
+        );
+
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+
+        set_context_callback(time_now, vec![PromiseResult::Successful(vec![])]);
+        let result = contract
+            .lot_after_claim_clean_up("alice".parse().unwrap(), LOT_CLEAN_UP_STEP_MAX_ITEMS_MANY);
+        assert_eq!(result, true, "expected clean up to succeed");
+        assert!(
+            contract.lot_get("alice".parse().unwrap()).is_none(),
+            "expected lot removed"
+        );
+        assert!(
+            contract
+                .lot_bundle_members
+                .get(&"alice-app".parse().unwrap())
+                .is_none(),
+            "expected bundle members released",
+        );
+    }
+
+    fn create_lot_alice_raffle_with_tickets_api(contract: &mut Contract) {
+        api_storage_deposit(contract, &"bob".parse().unwrap());
+        testing_env!(get_context_call(to_ts(10), &"alice".parse().unwrap()));
+        contract.lot_offer_raffle(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            Some(to_ts(17).into()),
+            None,
+        );
+        for (buyer_id, ts) in [("carol", 11), ("dan", 12), ("frank", 13)] {
+            api_storage_deposit(contract, &buyer_id.parse().unwrap());
+            testing_env!(get_context_pay(
+                to_ts(ts),
+                &buyer_id.parse().unwrap(),
+                to_yocto("2")
+            ));
+            contract.lot_raffle_buy_ticket("alice".parse().unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_api_lot_raffle_get() {
+        let mut contract = build_contract();
+        create_lot_alice_raffle_with_tickets_api(&mut contract);
+
+        testing_env!(get_context_view(to_ts(14)));
+        let lot = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert!(lot.is_raffle, "expected raffle lot");
+        assert_eq!(lot.status, "OnSale");
+
+        let raffle = contract
+            .lot_raffle_get("alice".parse().unwrap(), Some("dan".parse().unwrap()))
+            .unwrap();
+        assert_eq!(raffle.ticket_price, to_yocto("2").into());
+        assert_eq!(raffle.tickets_count, 3, "wrong tickets count");
+        assert!(raffle.has_ticket, "expected dan to hold a ticket");
+        assert_eq!(raffle.winner_id, None);
+
+        let raffle = contract
+            .lot_raffle_get("alice".parse().unwrap(), Some("eve".parse().unwrap()))
+            .unwrap();
+        assert!(!raffle.has_ticket, "expected eve to hold no ticket");
+    }
+
+    #[test]
+    pub fn test_api_lot_raffle_draw() {
+        let mut contract = build_contract();
+        create_lot_alice_raffle_with_tickets_api(&mut contract);
+
+        let mut seed = vec![0u8; 32];
+        seed[0] = 2;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id("eve".parse().unwrap())
+            .block_timestamp(to_ts(18))
+            .random_seed(seed)
+            .build());
+        let winner_id = contract.lot_raffle_draw("alice".parse().unwrap());
+        let frank: ProfileId = "frank".parse().unwrap();
+        assert_eq!(winner_id, frank, "expected ticket 2 % 3 to win");
+
+        assert_eq!(
+            check_rewards(&contract, &"bob".parse().unwrap()),
+            to_yocto("1.8")
+        );
+        assert_eq!(
+            check_rewards(&contract, &"carol".parse().unwrap()),
+            to_yocto("2")
+        );
+        assert_eq!(
+            check_rewards(&contract, &"dan".parse().unwrap()),
+            to_yocto("2")
+        );
+        assert_eq!(check_rewards(&contract, &frank), 0);
+
+        let lot = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(lot.status, "SaleSuccess");
+        assert_eq!(lot.claimer_id, Some(frank.clone()));
+        let raffle = contract
+            .lot_raffle_get("alice".parse().unwrap(), None)
+            .unwrap();
+        assert_eq!(raffle.winner_id, Some(frank.clone()));
+
+        testing_env!(get_context_call(to_ts(18), &frank));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "draw: expected status draw pending")]
+    pub fn test_api_lot_raffle_draw_fail_on_sale() {
+        let mut contract = build_contract();
+        create_lot_alice_raffle_with_tickets_api(&mut contract);
+
+        testing_env!(get_context_call(to_ts(14), &"eve".parse().unwrap()));
+        contract.lot_raffle_draw("alice".parse().unwrap());
+    }
+
+    #[test]
+    pub fn test_api_lot_after_claim_clean_up_watchers() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_sale_success();
+        contract.internal_lot_save(&lot);
+        let alice: LotId = "alice".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        api_storage_deposit(&mut contract, &eve);
+        let used_before = contract.storage_accounts.get(&eve).unwrap().used();
+        testing_env!(get_context_call(time_now, &eve));
+        contract.watch_add(alice.clone());
+        assert!(
+            contract.storage_accounts.get(&eve).unwrap().used() > used_before,
+            "expected watch charged to the watcher",
+        );
+
+        testing_env!(get_context_call(time_now, &"dan".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+        contract.lot_claim(alice.clone(), public_key, None);
+
+        set_context_callback(time_now, vec![PromiseResult::Successful(vec![])]);
+        let result = contract.lot_after_claim_clean_up(alice, LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert!(result, "expected clean up to succeed");
+        assert!(
+            contract.watch_list(eve.clone(), None, None).is_empty(),
+            "expected watchlist entry removed",
+        );
+        assert!(
+            contract.internal_profile_get(&eve).lots_watching.is_empty(),
+            "expected profile watchlist cleared",
+        );
+        assert_eq!(
+            contract.storage_accounts.get(&eve).unwrap().used(),
+            used_before,
+            "expected watcher storage released",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "claim by bidder: expected status sale success")]
+    pub fn test_api_lot_claim_fail_still_active() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"dan".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "claim by bidder: wrong claimer")]
+    pub fn test_api_lot_claim_fail_wrong_claimer() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        contract.internal_lot_save(&lot);
+        testing_env!(get_context_call(time_now, &"dan".parse().unwrap()));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+
+        contract.lot_claim("alice".parse().unwrap(), public_key, None);
+    }
+
+    #[test]
+    pub fn test_api_lot_withdraw_success() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
+        contract.lot_withdraw("alice".parse().unwrap());
+    }
+
+    fn api_lot_offer_with_relist_policy(contract: &mut Contract, rounds: u32) {
+        api_storage_deposit(contract, &"bob".parse().unwrap());
+        testing_env!(get_context_call(to_ts(10), &"alice".parse().unwrap()));
+        contract.lot_offer(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            Some(to_ts(17).into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some((rounds, FractionView { num: 1, denom: 4 })),
+        );
+    }
+
+    #[test]
+    fn test_api_lot_offer_relist_policy() {
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let mut contract = build_contract();
+        api_lot_offer_with_relist_policy(&mut contract, 3);
+
+        // view computes the current round without a transaction
+        testing_env!(get_context_view(to_ts(20)));
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(result.status, "OnSale", "expected lot relisted");
+        assert_eq!(result.relist_round, 1, "wrong relist round");
+        assert_eq!(result.relist_rounds_left, 2, "wrong relist rounds left");
+        assert_eq!(result.reserve_price, to_yocto("1.5").into());
+        assert_eq!(result.start_timestamp, to_ts(17).into());
+        assert_eq!(result.finish_timestamp, to_ts(24).into());
+        assert_eq!(result.next_bid_amount, Some(to_yocto("1.5").into()));
+
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: carol.clone(),
+                amount: to_yocto("1.5"),
+                timestamp: to_ts(20),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+
+        let lot = contract.lots.get(&alice).unwrap();
+        assert_eq!(lot.finish_timestamp, to_ts(24), "expected round stored");
+        assert_eq!(lot.reserve_price, to_yocto("1.5"), "expected round stored");
+
+        testing_env!(get_context_view(to_ts(40)));
+        let result = contract.lot_get(alice).unwrap();
+        assert_eq!(result.status, "SaleSuccess", "expected no relist with bids");
+        assert_eq!(result.relist_round, 1, "wrong relist round");
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_offer: too many relist rounds")]
+    fn test_api_lot_offer_relist_policy_fail_too_many_rounds() {
+        let mut contract = build_contract();
+        api_lot_offer_with_relist_policy(&mut contract, LOT_RELIST_MAX_ROUNDS + 1);
+    }
+
+    #[test]
+    fn test_api_lot_extend() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+
+        testing_env!(get_context_call(to_ts(16), &bob));
+        contract.lot_extend(alice.clone(), to_ts(30).into());
+
+        testing_env!(get_context_view(to_ts(20)));
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(result.finish_timestamp, to_ts(30).into());
+        assert_eq!(result.status, "OnSale", "expected lot still on sale");
+        assert!(result.is_active, "expected lot active");
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: bids exist")]
+    fn test_api_lot_extend_fail_bids_exist() {
+        let mut contract = create_lot_alice_with_bids_api();
+
+        testing_env!(get_context_call(to_ts(13), &"bob".parse().unwrap()));
+        contract.lot_extend("alice".parse().unwrap(), to_ts(30).into());
+    }
+
+    fn create_lot_alice_with_bids_api() -> Contract {
+        let mut contract = build_contract();
+        create_lot_x_sells_y_api(
+            &mut contract,
+            &"bob".parse().unwrap(),
+            &"alice".parse().unwrap(),
+        );
+        for (bidder_id, amount, timestamp) in [("carol", "5", 11), ("dan", "6", 12)] {
+            api_lot_bid(
+                &mut contract,
+                &"alice".parse().unwrap(),
+                &Bid {
+                    bidder_id: bidder_id.parse().unwrap(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(timestamp),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+
+        contract
+    }
+
+    #[test]
+    fn test_api_lot_cancel_with_penalty() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let mut contract = create_lot_alice_with_bids_api();
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("5.4"));
+        // rewards from another sale to cover the compensation
+        contract.internal_profile_rewards_transfer(&bob, to_yocto("1.5"));
+
+        // top bid 6 plus penalty 0.6
+        testing_env!(get_context_call(to_ts(13), &bob));
+        contract.lot_cancel_with_penalty(alice.clone());
+
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("0.3"));
+        assert_eq!(check_rewards(&contract, &carol), to_yocto("5.08"));
+        assert_eq!(check_rewards(&contract, &dan), to_yocto("6.6"));
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(result.status, "Cancelled", "wrong status");
+        assert!(result.is_cancelled, "expected cancelled");
+        assert_eq!(result.claimer_id, None, "expected no claimer");
+
+        testing_env!(get_context_call(to_ts(14), &bob));
+        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
+        contract.lot_claim(alice.clone(), public_key, None);
+        let result = contract.lot_get(alice).unwrap();
+        assert_eq!(result.status, "ClaimPending", "expected seller claim");
+    }
+
+    #[test]
+    fn test_api_lot_cancel_with_penalty_payouts() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let frank: ProfileId = "frank".parse().unwrap();
+        let mut contract = build_contract();
+
+        api_storage_deposit(&mut contract, &bob);
+        testing_env!(get_context_call(to_ts(10), &alice));
+        contract.lot_offer(
+            bob.clone(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            Some(to_ts(17).into()),
+            None,
+            None,
+            Some(vec![
+                (eve.clone(), FractionView { num: 1, denom: 3 }),
+                (frank.clone(), FractionView { num: 2, denom: 3 }),
+            ]),
+            None,
+            None,
+            None,
+            None,
+        );
+        for (bidder_id, amount, timestamp) in [("carol", "5", 11), ("dan", "6", 12)] {
+            api_lot_bid(
+                &mut contract,
+                &alice,
+                &Bid {
+                    bidder_id: bidder_id.parse().unwrap(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(timestamp),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+        assert_eq!(check_rewards(&contract, &eve), to_yocto("1.8"));
+        assert_eq!(check_rewards(&contract, &frank), to_yocto("3.6"));
+
+        contract.internal_profile_rewards_transfer(&bob, to_yocto("6.6"));
+
+        // compensation 6.6 is paid by the seller, payout beneficiaries keep the proceeds
+        testing_env!(get_context_call(to_ts(13), &bob));
+        contract.lot_cancel_with_penalty(alice);
+
+        assert_eq!(check_rewards(&contract, &bob), 0);
+        assert_eq!(check_rewards(&contract, &eve), to_yocto("1.8"));
+        assert_eq!(check_rewards(&contract, &frank), to_yocto("3.6"));
+        assert_eq!(check_rewards(&contract, &dan), to_yocto("6.6"));
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_cancel_with_penalty: not enough seller rewards")]
+    fn test_api_lot_cancel_with_penalty_fail_not_enough_rewards() {
+        let bob: ProfileId = "bob".parse().unwrap();
+        let mut contract = create_lot_alice_with_bids_api();
+        contract.internal_profile_rewards_transfer(&bob, to_yocto("1.2") - 1);
+
+        testing_env!(get_context_call(to_ts(13), &bob));
+        contract.lot_cancel_with_penalty("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_cancel_with_penalty: deposit not accepted")]
+    fn test_api_lot_cancel_with_penalty_fail_deposit() {
+        let bob: ProfileId = "bob".parse().unwrap();
+        let mut contract = create_lot_alice_with_bids_api();
+        contract.internal_profile_rewards_transfer(&bob, to_yocto("1.2"));
+
+        testing_env!(get_context_pay(to_ts(13), &bob, to_yocto("1.2")));
+        contract.lot_cancel_with_penalty("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "cancel: wrong caller")]
+    fn test_api_lot_cancel_with_penalty_fail_wrong_caller() {
+        let mut contract = create_lot_alice_with_bids_api();
+
+        testing_env!(get_context_call(to_ts(13), &"dan".parse().unwrap()));
+        contract.lot_cancel_with_penalty("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "withdraw: expected no bids")]
+    fn test_api_lot_withdraw_fail_has_bids() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids();
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
+        contract.lot_withdraw("alice".parse().unwrap());
+    }
+
+    #[test]
+    fn test_api_lot_offer_payouts() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let frank: ProfileId = "frank".parse().unwrap();
+        let mut contract = build_contract();
+
+        api_storage_deposit(&mut contract, &bob);
+        testing_env!(get_context_call(to_ts(10), &alice));
+        contract.lot_offer(
+            bob.clone(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            Some(to_ts(17).into()),
+            None,
+            None,
+            Some(vec![
+                (eve.clone(), FractionView { num: 1, denom: 3 }),
+                (frank.clone(), FractionView { num: 2, denom: 3 }),
+            ]),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.payouts,
+            vec![
+                (eve.clone(), FractionView { num: 1, denom: 3 }),
+                (frank.clone(), FractionView { num: 2, denom: 3 }),
+            ],
+            "wrong payouts",
+        );
+
+        // commission 1/10: to seller 7 NEAR + 1 yocto - 0.7 NEAR = 6.3 NEAR + 1 yocto
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: carol.clone(),
+                amount: to_yocto("7") + 1,
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+
+        let rewards = |contract: &Contract, profile_id: &ProfileId| -> Balance {
+            contract
+                .profile_get(profile_id.clone())
+                .rewards_available
+                .into()
+        };
+        assert_eq!(
+            rewards(&contract, &bob),
+            0,
+            "expected no rewards for seller"
+        );
+        assert_eq!(
+            rewards(&contract, &frank),
+            to_yocto("4.2"),
+            "wrong secondary beneficiary rewards",
+        );
+        assert_eq!(
+            rewards(&contract, &eve),
+            to_yocto("2.1") + 1,
+            "expected dust to primary beneficiary",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "payouts: expected shares sum to one")]
+    fn test_api_lot_offer_payouts_fail_sum_not_one() {
+        let mut contract = build_contract();
+
+        api_storage_deposit(&mut contract, &"bob".parse().unwrap());
+        testing_env!(get_context_call(to_ts(10), &"alice".parse().unwrap()));
+        contract.lot_offer(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            Some(to_ts(17).into()),
+            None,
+            None,
+            Some(vec![(
+                "eve".parse().unwrap(),
+                FractionView { num: 1, denom: 2 },
+            )]),
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    fn api_lot_offer_with_prev_bidder_commission_share(
+        contract: &mut Contract,
+        share: FractionView,
+    ) {
+        api_storage_deposit(contract, &"bob".parse().unwrap());
+        testing_env!(get_context_call(to_ts(10), &"alice".parse().unwrap()));
+        contract.lot_offer(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            Some(to_ts(17).into()),
+            None,
+            None,
+            None,
+            Some(share),
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn test_api_lot_offer_prev_bidder_commission_share() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let mut contract = build_contract();
+        api_lot_offer_with_prev_bidder_commission_share(
+            &mut contract,
+            FractionView { num: 1, denom: 2 },
+        );
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.prev_bidder_commission_share,
+            FractionView { num: 1, denom: 2 },
+            "wrong prev bidder commission share",
+        );
+
+        for (bidder_id, amount, timestamp) in [(&carol, "5", 11), (&dan, "8", 12)] {
+            api_lot_bid(
+                &mut contract,
+                &alice,
+                &Bid {
+                    bidder_id: bidder_id.clone(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(timestamp),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+
+        // commission 0.3, half of it to the outbid bidder
+        assert_eq!(check_rewards(&contract, &carol), to_yocto("5.15"));
+        assert_eq!(check_rewards(&contract, &bob), to_yocto("7.2"));
+    }
+
+    #[test]
+    fn test_api_lot_offer_prev_bidder_commission_share_default() {
+        let mut contract = build_contract();
+        let lot = create_lot_x_sells_y_api(
+            &mut contract,
+            &"bob".parse().unwrap(),
+            &"alice".parse().unwrap(),
+        );
+
+        testing_env!(get_context_view(to_ts(11)));
+        let result = contract.lot_get(lot.lot_id.clone()).unwrap();
+        assert_eq!(
+            result.prev_bidder_commission_share,
+            FractionView { num: 4, denom: 5 },
+            "expected contract default",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_offer: prev bidder commission share out of bounds")]
+    fn test_api_lot_offer_prev_bidder_commission_share_fail_out_of_bounds() {
+        let mut contract = build_contract();
+        api_lot_offer_with_prev_bidder_commission_share(
+            &mut contract,
+            FractionView { num: 0, denom: 1 },
+        );
+    }
+
+    #[test]
+    fn test_api_lot_reoffer_prev_bidder_commission_share() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let mut contract = build_contract();
+        api_lot_offer_with_prev_bidder_commission_share(
+            &mut contract,
+            FractionView { num: 1, denom: 2 },
+        );
+
+        testing_env!(get_context_call(to_ts(11), &bob));
+        contract.lot_reoffer(
+            alice.clone(),
+            to_yocto("3").into(),
+            to_yocto("20").into(),
+            Some(to_ts(30).into()),
+            None,
+            None,
+        );
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.prev_bidder_commission_share,
+            FractionView { num: 1, denom: 2 },
+            "expected share kept on reoffer",
+        );
+
+        testing_env!(get_context_call(to_ts(12), &bob));
+        contract.lot_reoffer(
+            alice.clone(),
+            to_yocto("3").into(),
+            to_yocto("20").into(),
+            Some(to_ts(30).into()),
+            None,
+            Some(FractionView { num: 1, denom: 4 }),
+        );
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.prev_bidder_commission_share,
+            FractionView { num: 1, denom: 4 },
+            "expected share updated on reoffer",
+        );
+    }
+
+    fn api_lot_offer_with_bid_step(
+        contract: &mut Contract,
+        bid_step: Option<FractionView>,
+        bid_step_min_increment: Option<Balance>,
+    ) {
+        api_storage_deposit(contract, &"bob".parse().unwrap());
+        testing_env!(get_context_call(to_ts(10), &"alice".parse().unwrap()));
+        contract.lot_offer(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            Some(to_ts(17).into()),
+            None,
+            None,
+            None,
+            None,
+            bid_step,
+            bid_step_min_increment.map(|x| x.into()),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_api_lot_offer_bid_step() {
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let mut contract = build_contract();
+        api_lot_offer_with_bid_step(
+            &mut contract,
+            Some(FractionView { num: 1, denom: 2 }),
+            Some(to_yocto("0.5")),
+        );
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.bid_step,
+            FractionView { num: 1, denom: 2 },
+            "wrong bid step"
+        );
+        assert_eq!(
+            result.bid_step_min_increment,
+            to_yocto("0.5").into(),
+            "wrong bid step min increment",
+        );
+
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: carol.clone(),
+                amount: to_yocto("4"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.next_bid_amount,
+            Some(to_yocto("6").into()),
+            "expected fractional step if greater than min increment",
+        );
+    }
+
+    #[test]
+    fn test_api_lot_offer_bid_step_min_increment() {
+        let alice: LotId = "alice".parse().unwrap();
+        let mut contract = build_contract();
+        api_lot_offer_with_bid_step(&mut contract, None, Some(to_yocto("1")));
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.bid_step,
+            FractionView { num: 1, denom: 5 },
+            "expected contract default bid step",
+        );
+
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("4"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(
+            result.next_bid_amount,
+            Some(to_yocto("5").into()),
+            "expected min increment if greater than fractional step",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bid: expected bigger bid")]
+    fn test_api_lot_offer_bid_step_fail_bid_too_small() {
+        let alice: LotId = "alice".parse().unwrap();
+        let mut contract = build_contract();
+        api_lot_offer_with_bid_step(&mut contract, None, Some(to_yocto("1")));
+
+        for (bidder_id, amount, timestamp) in [("carol", "4", 11), ("dan", "4.9", 12)] {
+            api_lot_bid(
+                &mut contract,
+                &alice,
+                &Bid {
+                    bidder_id: bidder_id.parse().unwrap(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(timestamp),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_offer: bid step out of bounds")]
+    fn test_api_lot_offer_bid_step_fail_out_of_bounds() {
+        let mut contract = build_contract();
+        api_lot_offer_with_bid_step(
+            &mut contract,
+            Some(FractionView { num: 1, denom: 20 }),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_offer: bid step min increment too big")]
+    fn test_api_lot_offer_bid_step_fail_min_increment_too_big() {
+        let mut contract = build_contract();
+        api_lot_offer_with_bid_step(&mut contract, None, Some(to_yocto("2")));
+    }
+
+    #[test]
+    fn test_api_lot_reoffer_success() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice();
+        api_storage_deposit(&mut contract, &lot.seller_id);
+        contract.internal_lot_save(&lot);
+
+        let new_reserve_price: Balance = to_yocto("1");
+        let new_buy_now_price: Balance = to_yocto("100");
+
+        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
+        contract.lot_reoffer(
+            "alice".parse().unwrap(),
+            new_reserve_price.into(),
+            new_buy_now_price.into(),
+            Some(to_ts(30).into()),
+            None,
+            None,
+        );
+
+        testing_env!(get_context_view(time_now));
+        let response: Vec<LotView> = contract.lot_list(None, None);
+        assert_eq!(response.len(), 1, "expected empty lot list");
+        let lot_updated = response.into_iter().next().unwrap();
+
+        assert_eq!(lot_updated.lot_id, lot.lot_id.clone());
+        assert_eq!(lot_updated.seller_id, lot.seller_id.clone());
+        assert_eq!(lot_updated.reserve_price, new_reserve_price.into());
+        assert_eq!(lot_updated.buy_now_price, new_buy_now_price.into());
+        assert_eq!(lot_updated.start_timestamp, time_now.into());
+        assert_eq!(lot_updated.finish_timestamp, to_ts(30).into());
+    }
+
+    #[test]
+    fn test_api_lot_reoffer_success_by_winner() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        for (bidder_id, amount) in [(&carol, "3"), (&dan, "6")] {
+            api_lot_bid(
+                &mut contract,
+                &alice,
+                &Bid {
+                    bidder_id: bidder_id.clone(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(11),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+
+        let time_now = to_ts(18);
+        testing_env!(get_context_call(time_now, &dan));
+        contract.lot_reoffer(
+            alice.clone(),
+            to_yocto("7").into(),
+            to_yocto("20").into(),
+            Some(to_ts(30).into()),
+            None,
+            None,
+        );
+
+        testing_env!(get_context_view(time_now));
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(result.seller_id, dan, "expected winner to be the seller");
+        assert_eq!(result.status, "OnSale", "wrong status");
+        assert_eq!(result.last_bid_amount, None, "expected no bids");
+        assert!(
+            contract.lot_bid_list(alice.clone()).is_empty(),
+            "expected no bids"
+        );
+        assert!(
+            contract.lot_list_offering_by(bob, None, None).is_empty(),
+            "expected no offering lots for prev seller",
+        );
+        for bidder_id in [&carol, &dan] {
+            assert!(
+                contract
+                    .lot_list_bidding_by(bidder_id.clone(), None, None)
+                    .is_empty(),
+                "expected no bidding lots",
+            );
+        }
+        let result = contract.lot_list_offering_by(dan, None, None);
+        assert_eq!(result.len(), 1, "expected lot offered by the winner");
+    }
+
+    #[test]
+    fn test_api_lot_reoffer_by_winner_relist_pending() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        for (bidder_id, amount) in [(&carol, "3"), (&dan, "6")] {
+            api_lot_bid(
+                &mut contract,
+                &alice,
+                &Bid {
+                    bidder_id: bidder_id.clone(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(11),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+
+        // bid history doesn't fit into a single step
+        testing_env!(get_context_call(to_ts(18), &dan));
+        let mut lot = contract.internal_lot_extract(&alice);
+        lot.relist_pending = Some(LotRelist {
+            seller_id: dan.clone(),
+            reserve_price: to_yocto("7"),
+            buy_now_price: to_yocto("20"),
+            duration: to_ts(24) - to_ts(19),
+            prev_bidder_commission_share: None,
+        });
+        let result = contract.internal_lot_clean_up_step(lot, 1);
+        assert_eq!(result, false, "expected relist pending");
+        assert!(
+            contract.lot_get(alice.clone()).is_none(),
+            "expected lot hidden"
+        );
+
+        testing_env!(get_context_call(to_ts(19), &carol));
+        let result = contract.lot_clean_up_step(alice.clone(), 10);
+        assert_eq!(result, true, "expected lot relisted");
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(result.seller_id, dan, "expected winner to be the seller");
+        assert_eq!(result.status, "OnSale", "wrong status");
+        assert_eq!(result.start_timestamp, to_ts(19).into());
+        assert_eq!(result.finish_timestamp, to_ts(24).into());
+        assert!(contract.lot_bid_list(alice.clone()).is_empty());
+        assert_eq!(contract.lot_list_offering_by(dan, None, None).len(), 1);
+        assert!(contract.lot_list_bidding_by(carol, None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: bids exist")]
+    fn test_api_lot_reoffer_fail_has_bids() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice();
+        let (lot, time_now) = create_lot_alice_with_bids();
         contract.internal_lot_save(&lot);
 
-        testing_env!(get_context_view(time_now));
-        let alice: LotId = "alice".parse().unwrap();
-        let result = contract.lot_get(alice.clone());
-        assert!(result.is_some(), "expected: lot present");
-        let result = result.unwrap();
-        assert_eq!(result.lot_id, alice.clone());
+        let new_reserve_price: Balance = to_yocto("1");
+        let new_buy_now_price: Balance = to_yocto("100");
+
+        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
+        contract.lot_reoffer(
+            "alice".parse().unwrap(),
+            new_reserve_price.into(),
+            new_buy_now_price.into(),
+            Some(to_ts(30).into()),
+            None,
+            None,
+        );
     }
 
     #[test]
-    pub fn test_api_lot_get_not_present() {
+    pub fn test_api_lot_remove_unsafe_success() {
         let mut contract = build_contract();
         let (lot, time_now) = create_lot_alice();
         contract.internal_lot_save(&lot);
 
-        testing_env!(get_context_view(time_now));
-        let bob: LotId = "bob".parse().unwrap();
-        let result = contract.lot_get(bob.clone());
-        assert!(result.is_none(), "expected: lot not present");
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_remove_unsafe("alice".parse().unwrap());
     }
 
     #[test]
-    #[should_panic(expected = "bid: expected bigger bid")]
-    pub fn test_api_lot_bid_fail_small_bid() {
+    #[should_panic(expected = "lot_remove_unsafe: lot on grace period, wait")]
+    pub fn test_api_lot_remove_unsafe_fail_grace_period() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice();
+        let (lot, _) = create_lot_alice();
+        let time_now = lot.start_timestamp + LOT_REMOVE_UNSAFE_GRACE_DURATION - 1;
         contract.internal_lot_save(&lot);
 
-        api_lot_bid(
-            &mut contract,
-            &"alice".parse().unwrap(),
-            &Bid {
-                bidder_id: "carol".parse().unwrap(),
-                amount: to_yocto("1"),
-                timestamp: time_now,
-            },
-        );
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_remove_unsafe("alice".parse().unwrap());
     }
 
     #[test]
-    #[should_panic(expected = "bid: expected status active")]
-    pub fn test_api_lot_bid_fail_inactive() {
+    #[should_panic(expected = "lot_remove_unsafe: lot has bids")]
+    pub fn test_api_lot_remove_unsafe_fail_lot_has_bids() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_with_bids_sale_success();
+        let (lot, time_now) = create_lot_alice_with_bids();
         contract.internal_lot_save(&lot);
 
-        api_lot_bid(
-            &mut contract,
-            &"alice".parse().unwrap(),
-            &Bid {
-                bidder_id: "carol".parse().unwrap(),
-                amount: to_yocto("10"),
-                timestamp: time_now,
-            },
-        );
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_remove_unsafe("alice".parse().unwrap());
     }
 
-    const NEW_PUBLIC_KEY: &str = "ed25519:KEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYK";
+    fn lock_info_result(owner_id: &str, is_unlocked: bool) -> PromiseResult {
+        let lock_info = LockInfoView {
+            owner_id: owner_id.parse().unwrap(),
+            original_owner_id: "marketplace".parse().unwrap(),
+            lock_timestamp: to_ts(5).into(),
+            is_unlocked,
+        };
+        PromiseResult::Successful(serde_json::to_vec(&lock_info).unwrap())
+    }
 
     #[test]
-    pub fn test_api_lot_claim_success() {
+    pub fn test_api_lot_after_remove_unsafe_remove_not_owned() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        let (lot, time_now) = create_lot_alice();
         contract.internal_lot_save(&lot);
-        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
-        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
 
-        contract.lot_claim("alice".parse().unwrap(), public_key);
+        set_context_callback(time_now, vec![lock_info_result("bob", false)]);
+        let result = contract.lot_after_remove_unsafe_remove("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Value(true)),
+            "expected removed"
+        );
+        assert!(contract.lot_get("alice".parse().unwrap()).is_none());
     }
 
     #[test]
-    pub fn test_api_lot_claim_success_by_seller_withdrawn() {
+    #[should_panic(expected = "lot_remove_unsafe: lot seems safe")]
+    pub fn test_api_lot_after_remove_unsafe_remove_fail_seems_safe() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_withdrawn();
+        let (lot, time_now) = create_lot_alice();
         contract.internal_lot_save(&lot);
-        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
-        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
 
-        contract.lot_claim("alice".parse().unwrap(), public_key);
+        set_context_callback(time_now, vec![lock_info_result("marketplace", false)]);
+        contract.lot_after_remove_unsafe_remove("alice".parse().unwrap());
     }
 
     #[test]
-    #[should_panic(expected = "claim by bidder: expected status sale success")]
-    pub fn test_api_lot_claim_fail_still_active() {
+    pub fn test_api_lot_after_remove_unsafe_remove_unknown() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_with_bids();
+        let (lot, time_now) = create_lot_alice();
         contract.internal_lot_save(&lot);
-        testing_env!(get_context_call(time_now, &"dan".parse().unwrap()));
-        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
 
-        contract.lot_claim("alice".parse().unwrap(), public_key);
+        set_context_callback(
+            time_now,
+            vec![PromiseResult::Successful(b"\"garbage\"".to_vec())],
+        );
+        let result = contract.lot_after_remove_unsafe_remove("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected kept"
+        );
+        assert!(contract.lot_get("alice".parse().unwrap()).is_some());
+
+        // no get_lock_info, falling back to get_owner
+        set_context_callback(time_now, vec![PromiseResult::Failed]);
+        let result = contract.lot_after_remove_unsafe_remove("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Promise(_)),
+            "expected fallback"
+        );
+        assert!(contract.lot_get("alice".parse().unwrap()).is_some());
+
+        set_context_callback(time_now, vec![PromiseResult::Successful(b"42".to_vec())]);
+        let result = contract.lot_after_remove_unsafe_get_owner("alice".parse().unwrap());
+        assert_eq!(result, false, "expected kept");
+        assert!(contract.lot_get("alice".parse().unwrap()).is_some());
     }
 
     #[test]
-    #[should_panic(expected = "claim by bidder: wrong claimer")]
-    pub fn test_api_lot_claim_fail_wrong_claimer() {
+    #[should_panic(expected = "lot_remove_unsafe: lot seems safe")]
+    pub fn test_api_lot_after_remove_unsafe_get_owner_fail_seems_safe() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_buy_now_bid();
+        let (lot, time_now) = create_lot_alice();
         contract.internal_lot_save(&lot);
-        testing_env!(get_context_call(time_now, &"dan".parse().unwrap()));
-        let public_key: PublicKey = NEW_PUBLIC_KEY.parse().unwrap();
 
-        contract.lot_claim("alice".parse().unwrap(), public_key);
+        set_context_callback(
+            time_now,
+            vec![PromiseResult::Successful(b"\"marketplace\"".to_vec())],
+        );
+        contract.lot_after_remove_unsafe_get_owner("alice".parse().unwrap());
     }
 
     #[test]
-    pub fn test_api_lot_withdraw_success() {
+    pub fn test_api_lot_after_remove_unsafe_get_owner_not_owned() {
         let mut contract = build_contract();
         let (lot, time_now) = create_lot_alice();
         contract.internal_lot_save(&lot);
 
-        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
-        contract.lot_withdraw("alice".parse().unwrap());
+        set_context_callback(
+            time_now,
+            vec![PromiseResult::Successful(b"\"bob\"".to_vec())],
+        );
+        let result = contract.lot_after_remove_unsafe_get_owner("alice".parse().unwrap());
+        assert_eq!(result, true, "expected removed");
+        assert!(contract.lot_get("alice".parse().unwrap()).is_none());
     }
 
     #[test]
-    #[should_panic(expected = "withdraw: expected no bids")]
-    fn test_api_lot_withdraw_fail_has_bids() {
+    #[should_panic(expected = "lot_reconcile: lot is still active")]
+    pub fn test_api_lot_reconcile_fail_still_active() {
         let mut contract = build_contract();
         let (lot, time_now) = create_lot_alice_with_bids();
         contract.internal_lot_save(&lot);
 
-        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
-        contract.lot_withdraw("alice".parse().unwrap());
+        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
+        contract.lot_reconcile("alice".parse().unwrap());
     }
 
     #[test]
-    fn test_api_lot_reoffer_success() {
+    pub fn test_api_lot_after_reconcile_still_owned() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice();
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
         contract.internal_lot_save(&lot);
 
-        let new_reserve_price: Balance = to_yocto("1");
-        let new_buy_now_price: Balance = to_yocto("100");
+        set_context_callback(time_now, vec![lock_info_result("marketplace", false)]);
+        let result = contract.lot_after_reconcile("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected lot to stay"
+        );
 
-        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
-        contract.lot_reoffer(
-            "alice".parse().unwrap(),
-            new_reserve_price.into(),
-            new_buy_now_price.into(),
-            Some(to_ts(30).into()),
-            None,
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(
+            result.status, "ClaimPending",
+            "expected claim kept within grace"
         );
 
-        testing_env!(get_context_view(time_now));
-        let response: Vec<LotView> = contract.lot_list(None, None);
-        assert_eq!(response.len(), 1, "expected empty lot list");
-        let lot_updated = response.into_iter().next().unwrap();
+        set_context_callback(
+            time_now + LOT_CLAIM_PENDING_GRACE_DURATION,
+            vec![lock_info_result("marketplace", false)],
+        );
+        let result = contract.lot_after_reconcile("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected lot to stay"
+        );
 
-        assert_eq!(lot_updated.lot_id, lot.lot_id.clone());
-        assert_eq!(lot_updated.seller_id, lot.seller_id.clone());
-        assert_eq!(lot_updated.reserve_price, new_reserve_price.into());
-        assert_eq!(lot_updated.buy_now_price, new_buy_now_price.into());
-        assert_eq!(lot_updated.start_timestamp, time_now.into());
-        assert_eq!(lot_updated.finish_timestamp, to_ts(30).into());
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "SaleSuccess", "expected claim reverted");
     }
 
     #[test]
-    #[should_panic(expected = "reoffer: bids exist")]
-    fn test_api_lot_reoffer_fail_has_bids() {
+    pub fn test_api_lot_after_reconcile_unknown() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_with_bids();
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
         contract.internal_lot_save(&lot);
+        let time_now = time_now + LOT_CLAIM_PENDING_GRACE_DURATION;
 
-        let new_reserve_price: Balance = to_yocto("1");
-        let new_buy_now_price: Balance = to_yocto("100");
+        set_context_callback(
+            time_now,
+            vec![PromiseResult::Successful(b"\"garbage\"".to_vec())],
+        );
+        let result = contract.lot_after_reconcile("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Value(false)),
+            "expected lot to stay"
+        );
 
-        testing_env!(get_context_call(time_now, &"bob".parse().unwrap()));
-        contract.lot_reoffer(
-            "alice".parse().unwrap(),
-            new_reserve_price.into(),
-            new_buy_now_price.into(),
-            Some(to_ts(30).into()),
-            None,
+        // no get_lock_info, falling back to get_owner
+        set_context_callback(time_now, vec![PromiseResult::Failed]);
+        let result = contract.lot_after_reconcile("alice".parse().unwrap());
+        assert!(
+            matches!(result, PromiseOrValue::Promise(_)),
+            "expected fallback"
         );
+
+        set_context_callback(time_now, vec![PromiseResult::Successful(b"42".to_vec())]);
+        let result = contract.lot_after_reconcile_get_owner("alice".parse().unwrap(), false);
+        assert_eq!(result, false, "expected lot to stay");
+
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "ClaimPending", "expected claim kept");
     }
 
     #[test]
-    pub fn test_api_lot_remove_unsafe_success() {
+    pub fn test_api_lot_after_reconcile_get_owner() {
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice();
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
         contract.internal_lot_save(&lot);
+        let time_now = time_now + LOT_CLAIM_PENDING_GRACE_DURATION;
 
-        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
-        contract.lot_remove_unsafe("alice".parse().unwrap());
+        set_context_callback(
+            time_now,
+            vec![PromiseResult::Successful(b"\"marketplace\"".to_vec())],
+        );
+        let result = contract.lot_after_reconcile_get_owner("alice".parse().unwrap(), false);
+        assert_eq!(result, false, "expected lot to stay");
+
+        let result = contract.lot_get("alice".parse().unwrap()).unwrap();
+        assert_eq!(result.status, "SaleSuccess", "expected claim reverted");
+
+        set_context_callback(time_now, vec![PromiseResult::Failed]);
+        let result = contract.lot_after_reconcile_get_owner("alice".parse().unwrap(), true);
+        assert_eq!(
+            result, false,
+            "expected lot to stay with other accounts owned"
+        );
+
+        let result = contract.lot_after_reconcile_get_owner("alice".parse().unwrap(), false);
+        assert_eq!(result, true, "expected lot without lock contract removed");
+        assert!(contract.lot_get("alice".parse().unwrap()).is_none());
     }
 
     #[test]
-    #[should_panic(expected = "lot_remove_unsafe: lot on grace period, wait")]
-    pub fn test_api_lot_remove_unsafe_fail_grace_period() {
+    pub fn test_api_lot_after_reconcile_not_owned() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
         let mut contract = build_contract();
-        let (lot, _) = create_lot_alice();
-        let time_now = lot.start_timestamp + LOT_REMOVE_UNSAFE_GRACE_DURATION - 1;
-        contract.internal_lot_save(&lot);
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: carol.clone(),
+                amount: to_yocto("10"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
 
-        testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
-        contract.lot_remove_unsafe("alice".parse().unwrap());
+        for promise_result in [
+            lock_info_result("alice", true),
+            lock_info_result("bob", false),
+        ] {
+            set_context_callback(to_ts(12), vec![promise_result]);
+            let result = contract.lot_after_reconcile(alice.clone());
+            assert!(
+                matches!(result, PromiseOrValue::Value(true)),
+                "expected lot to be removed"
+            );
+            assert!(contract.lot_get(alice.clone()).is_none(), "expected no lot");
+            assert!(
+                contract
+                    .lot_list_bidding_by(carol.clone(), None, None)
+                    .is_empty(),
+                "expected no bidding lots",
+            );
+            assert!(
+                contract
+                    .lot_list_offering_by(bob.clone(), None, None)
+                    .is_empty(),
+                "expected no offering lots",
+            );
+
+            create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        }
     }
 
     #[test]
-    #[should_panic(expected = "lot_remove_unsafe: lot has bids")]
-    pub fn test_api_lot_remove_unsafe_fail_lot_has_bids() {
+    pub fn test_api_lot_clean_up_step() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
         let mut contract = build_contract();
-        let (lot, time_now) = create_lot_alice_with_bids();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        for (i, amount) in ["2", "3", "4", "5", "6"].iter().enumerate() {
+            let bidder_id = if i % 2 == 0 { &carol } else { &dan };
+            api_lot_bid(
+                &mut contract,
+                &alice,
+                &Bid {
+                    bidder_id: bidder_id.clone(),
+                    amount: to_yocto(amount),
+                    timestamp: to_ts(11 + i as u64),
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+            );
+        }
+
+        let lot = contract.internal_lot_extract(&alice);
+        let result = contract.internal_lot_clean_up(lot, 1);
+        assert_eq!(result, false, "expected clean up not finished");
+
+        testing_env!(get_context_view(to_ts(18)));
+        assert!(
+            contract.lot_get(alice.clone()).is_none(),
+            "expected lot hidden"
+        );
+        assert!(
+            contract.lot_bid_list(alice.clone()).is_empty(),
+            "expected bids hidden"
+        );
+        assert!(
+            contract.lot_list(None, None).is_empty(),
+            "expected lot hidden"
+        );
+        for profile_id in [&bob, &carol, &dan] {
+            assert!(
+                contract
+                    .lot_list_offering_by(profile_id.clone(), None, None)
+                    .is_empty(),
+                "expected no offering lots",
+            );
+            assert!(
+                contract
+                    .lot_list_bidding_by(profile_id.clone(), None, None)
+                    .is_empty(),
+                "expected no bidding lots",
+            );
+        }
+
+        testing_env!(get_context_call(to_ts(18), &carol));
+        let result = contract.lot_clean_up_step(alice.clone(), 2);
+        assert_eq!(result, false, "expected clean up not finished");
+        assert_eq!(
+            contract.lots.get(&alice).unwrap().bids().len(),
+            2,
+            "wrong bids len"
+        );
+
+        let result = contract.lot_clean_up_step(alice.clone(), 10);
+        assert_eq!(result, true, "expected clean up finished");
+        assert!(contract.lot_get(alice.clone()).is_none(), "expected no lot");
+    }
+
+    #[test]
+    #[should_panic(expected = "lot_clean_up_step: lot is not cleaning up")]
+    pub fn test_api_lot_clean_up_step_fail_not_cleaning_up() {
+        let mut contract = build_contract();
+        let (lot, time_now) = create_lot_alice_with_bids_sale_success();
         contract.internal_lot_save(&lot);
 
         testing_env!(get_context_call(time_now, &"carol".parse().unwrap()));
-        contract.lot_remove_unsafe("alice".parse().unwrap());
+        contract.lot_clean_up_step("alice".parse().unwrap(), 10);
+    }
+
+    #[test]
+    pub fn test_api_lot_transfer_claim() {
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let mut contract = build_contract();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        api_lot_bid(
+            &mut contract,
+            &alice,
+            &Bid {
+                bidder_id: carol.clone(),
+                amount: to_yocto("10"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+        );
+
+        testing_env!(get_context_call(to_ts(12), &carol));
+        contract.lot_transfer_claim(alice.clone(), eve.clone());
+
+        let result = contract.lot_get(alice.clone()).unwrap();
+        assert_eq!(result.claimer_id, Some(eve.clone()), "wrong claimer");
+        assert_eq!(
+            result.last_bidder_id,
+            Some(carol.clone()),
+            "wrong last bidder"
+        );
+        assert!(
+            contract.lot_list_bidding_by(carol, None, None).is_empty(),
+            "expected no bidding lots for prev owner",
+        );
+        let result = contract.lot_list_bidding_by(eve.clone(), None, None);
+        assert_eq!(result.len(), 1, "expected lot for new owner");
+
+        let lot = contract.internal_lot_extract(&alice);
+        contract.internal_lot_clean_up(lot, LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert!(
+            contract.lot_list_bidding_by(eve, None, None).is_empty(),
+            "expected no bidding lots for new owner after clean up",
+        );
     }
 }
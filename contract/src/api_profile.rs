@@ -1,6 +1,8 @@
 use crate::*;
 
 pub const ERR_PROFILE_REWARDS_CLAIM_NOT_ENOUGH: &str = "profile_rewards_claim: not enough rewards";
+pub const ERR_WATCH_ADD_ALREADY_WATCHING: &str = "watch_add: already watching";
+pub const ERR_WATCH_REMOVE_NOT_WATCHING: &str = "watch_remove: not watching";
 
 pub const MIN_PROFILE_REWARDS_CLAIM_AMOUNT: Balance = 10 * 10u128.pow(21);
 
@@ -12,6 +14,7 @@ pub struct ProfileView {
     pub profile_id: ProfileId,
     pub rewards_available: WrappedBalance,
     pub rewards_claimed: WrappedBalance,
+    pub referral_rewards_total: WrappedBalance,
 }
 
 impl From<&Profile> for ProfileView {
@@ -20,6 +23,7 @@ impl From<&Profile> for ProfileView {
             profile_id: p.profile_id.clone(),
             rewards_available: p.rewards_available().into(),
             rewards_claimed: p.rewards_claimed().into(),
+            referral_rewards_total: p.referral_rewards_total().into(),
         }
     }
 }
@@ -38,6 +42,48 @@ impl Contract {
         profile.rewards_transfer(value);
         self.internal_profile_save(&profile);
     }
+
+    pub(crate) fn internal_profile_referral_rewards_transfer(
+        &mut self,
+        profile_id: &ProfileId,
+        value: Balance,
+    ) {
+        if value == 0 {
+            return;
+        }
+
+        let mut profile = self.internal_profile_extract(profile_id);
+        profile.referral_rewards_transfer(value);
+        self.internal_profile_save(&profile);
+    }
+
+    pub(crate) fn internal_profile_rewards_withdraw(
+        &mut self,
+        profile_id: &ProfileId,
+        value: Balance,
+    ) {
+        if value == 0 {
+            return;
+        }
+
+        let mut profile = self.internal_profile_extract(profile_id);
+        profile.rewards_withdraw(value);
+        self.internal_profile_save(&profile);
+    }
+
+    pub(crate) fn internal_profile_referral_rewards_withdraw(
+        &mut self,
+        profile_id: &ProfileId,
+        value: Balance,
+    ) {
+        if value == 0 {
+            return;
+        }
+
+        let mut profile = self.internal_profile_extract(profile_id);
+        profile.referral_rewards_withdraw(value);
+        self.internal_profile_save(&profile);
+    }
 }
 
 #[near_bindgen]
@@ -80,13 +126,82 @@ impl Contract {
         }
         rewards_transferred
     }
+
+    // Adds the lot to the caller watchlist. The used storage is charged to the caller
+    // storage balance
+    pub fn watch_add(&mut self, lot_id: LotId) -> bool {
+        let profile_id: ProfileId = env::predecessor_account_id();
+        let storage_usage = env::storage_usage();
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        assert!(lot.watch(&profile_id), "{}", ERR_WATCH_ADD_ALREADY_WATCHING);
+        self.internal_lot_save(&lot);
+
+        let mut profile = self.internal_profile_extract(&profile_id);
+        profile.lots_watching.insert(&lot_id);
+        self.internal_profile_save(&profile);
+        let storage_charged = self.internal_storage_charge(&profile_id, storage_usage);
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        lot.watch_storage_charged(&profile_id, storage_charged);
+        self.internal_lot_save(&lot);
+
+        true
+    }
+
+    // Removes the lot from the caller watchlist, charged storage is released to the caller
+    // storage balance
+    pub fn watch_remove(&mut self, lot_id: LotId) -> bool {
+        let profile_id: ProfileId = env::predecessor_account_id();
+
+        let mut profile = self.internal_profile_extract(&profile_id);
+        assert!(
+            profile.lots_watching.remove(&lot_id),
+            "{}",
+            ERR_WATCH_REMOVE_NOT_WATCHING,
+        );
+        self.internal_profile_save(&profile);
+
+        let mut lot = self.internal_lot_extract(&lot_id);
+        let storage_charged = lot.unwatch(&profile_id).unwrap_or(0);
+        self.internal_lot_save(&lot);
+        self.internal_storage_release(&profile_id, storage_charged);
+
+        true
+    }
+
+    pub fn watch_list(
+        &self,
+        profile_id: ProfileId,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Vec<LotView> {
+        let profile = self.internal_profile_get(&profile_id);
+        let time_now = env::block_timestamp();
+        let vector = profile.lots_watching.as_vector();
+
+        let idx_from = offset.unwrap_or(0);
+        let idx_to = limit.map(|x| idx_from + x).unwrap_or(u64::MAX);
+        let idx_to = std::cmp::min(idx_to, vector.len());
+
+        // removed lots and lots being cleaned up are skipped, page may contain less than
+        // limit items
+        (idx_from..idx_to)
+            .filter_map(|idx| self.lots.get(&vector.get(idx).unwrap()))
+            .filter(|lot| !lot.is_cleaning_up)
+            .map(|lot| (&lot, time_now, self).into())
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::tests::*;
 
+    use crate::api_lot::tests::*;
+    use crate::api_storage::tests::*;
     use crate::contract::tests_profile::*;
+    use crate::lot::tests::*;
 
     #[test]
     fn test_api_profile_internal_rewards_transfer() {
@@ -162,4 +277,138 @@ mod tests {
         testing_env!(get_context_call(to_ts(11), &profile_id));
         contract.profile_rewards_claim();
     }
+
+    fn api_watch_add(contract: &mut Contract, profile_id: &ProfileId, lot_id: &LotId) {
+        api_storage_deposit(contract, profile_id);
+        testing_env!(get_context_call(to_ts(11), profile_id));
+        contract.watch_add(lot_id.clone());
+    }
+
+    #[test]
+    pub fn test_api_watch_add_remove() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        api_storage_deposit(&mut contract, &carol);
+        let used_before = contract.storage_accounts.get(&carol).unwrap().used();
+
+        api_watch_add(&mut contract, &carol, &alice);
+        let result = contract.watch_list(carol.clone(), None, None);
+        assert_eq!(result.len(), 1, "expected lot in watchlist");
+        assert_eq!(result[0].lot_id, alice);
+        assert_eq!(contract.lots.get(&alice).unwrap().watchers_count(), 1);
+        assert!(
+            contract.storage_accounts.get(&carol).unwrap().used() > used_before,
+            "expected watch charged to the watcher",
+        );
+
+        testing_env!(get_context_call(to_ts(12), &carol));
+        contract.watch_remove(alice.clone());
+        assert!(contract.watch_list(carol.clone(), None, None).is_empty());
+        assert_eq!(contract.lots.get(&alice).unwrap().watchers_count(), 0);
+        assert_eq!(
+            contract.storage_accounts.get(&carol).unwrap().used(),
+            used_before,
+            "expected watch storage released",
+        );
+    }
+
+    #[test]
+    pub fn test_api_watch_kept_by_reoffer() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        api_watch_add(&mut contract, &carol, &alice);
+
+        testing_env!(get_context_call(to_ts(18), &bob));
+        contract.lot_reoffer(
+            alice.clone(),
+            to_yocto("1").into(),
+            to_yocto("5").into(),
+            Some(to_ts(30).into()),
+            None,
+            None,
+        );
+
+        testing_env!(get_context_view(to_ts(19)));
+        let result = contract.watch_list(carol.clone(), None, None);
+        assert_eq!(result.len(), 1, "expected lot kept in watchlist");
+        assert_eq!(result[0].finish_timestamp, to_ts(30).into());
+        assert_eq!(contract.lots.get(&alice).unwrap().watchers_count(), 1);
+
+        testing_env!(get_context_call(to_ts(19), &carol));
+        contract.watch_remove(alice.clone());
+        assert_eq!(contract.lots.get(&alice).unwrap().watchers_count(), 0);
+    }
+
+    #[test]
+    pub fn test_api_watch_list_skips_removed() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+
+        api_watch_add(&mut contract, &carol, &alice);
+        contract.lots.remove(&alice);
+
+        testing_env!(get_context_view(to_ts(12)));
+        assert!(contract.watch_list(carol, None, None).is_empty());
+    }
+
+    #[test]
+    pub fn test_api_watch_list_skips_cleaning_up() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+
+        api_watch_add(&mut contract, &carol, &alice);
+        let mut lot = contract.internal_lot_extract(&alice);
+        lot.is_cleaning_up = true;
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_view(to_ts(12)));
+        assert!(contract.watch_list(carol, None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "watch_add: already watching")]
+    pub fn test_api_watch_add_fail_already_watching() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+
+        api_watch_add(&mut contract, &carol, &alice);
+        api_watch_add(&mut contract, &carol, &alice);
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: account is not registered")]
+    pub fn test_api_watch_add_fail_not_registered() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_call(to_ts(11), &"carol".parse().unwrap()));
+        contract.watch_add("alice".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "watch_remove: not watching")]
+    pub fn test_api_watch_remove_fail_not_watching() {
+        let mut contract = build_contract();
+        let (lot, _) = create_lot_alice();
+        contract.internal_lot_save(&lot);
+
+        testing_env!(get_context_call(to_ts(11), &"carol".parse().unwrap()));
+        contract.watch_remove("alice".parse().unwrap());
+    }
 }
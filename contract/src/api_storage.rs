@@ -0,0 +1,549 @@
+use crate::*;
+
+pub const ERR_STORAGE_DEPOSIT_TOO_SMALL: &str = "storage_deposit: deposit is less than the min";
+pub const ERR_STORAGE_NOT_REGISTERED: &str = "storage: account is not registered";
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: WrappedBalance,
+    pub available: WrappedBalance,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: WrappedBalance,
+    pub max: Option<WrappedBalance>,
+}
+
+impl From<&StorageAccount> for StorageBalance {
+    fn from(a: &StorageAccount) -> Self {
+        Self {
+            total: a.total().into(),
+            available: a.available().into(),
+        }
+    }
+}
+
+impl Contract {
+    // Charges the storage used since storage_usage_before to the account balance,
+    // released storage is returned to the balance. Returns the charged bytes
+    pub(crate) fn internal_storage_charge(
+        &mut self,
+        account_id: &AccountId,
+        storage_usage_before: StorageUsage,
+    ) -> StorageUsage {
+        let mut account = self
+            .storage_accounts
+            .get(account_id)
+            .expect(ERR_STORAGE_NOT_REGISTERED);
+        let storage_usage = env::storage_usage();
+        let charged = storage_usage.saturating_sub(storage_usage_before);
+        if storage_usage > storage_usage_before {
+            account.charge(charged);
+        } else {
+            account.release(storage_usage_before - storage_usage);
+        }
+        self.storage_accounts.insert(account_id, &account);
+
+        charged
+    }
+
+    // Returns the storage charged for a removed record to the account balance
+    pub(crate) fn internal_storage_release(
+        &mut self,
+        account_id: &AccountId,
+        storage_usage: StorageUsage,
+    ) {
+        if storage_usage == 0 {
+            return;
+        }
+        let mut account = self
+            .storage_accounts
+            .get(account_id)
+            .expect(ERR_STORAGE_NOT_REGISTERED);
+        account.release(storage_usage);
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    // Charges the lot payer for the lot storage changed since storage_usage_before, the lot
+    // keeps the net charged bytes. Both measurements are taken with the lot saved
+    pub(crate) fn internal_storage_charge_lot(
+        &mut self,
+        lot_id: &LotId,
+        storage_usage_before: StorageUsage,
+    ) {
+        let storage_usage = env::storage_usage();
+        if storage_usage == storage_usage_before {
+            return;
+        }
+        let mut lot = self.internal_lot_extract(lot_id);
+        let payer_id = lot.storage_payer_id().clone();
+        let mut account = self
+            .storage_accounts
+            .get(&payer_id)
+            .expect(ERR_STORAGE_NOT_REGISTERED);
+        if storage_usage > storage_usage_before {
+            let charged = storage_usage - storage_usage_before;
+            account.charge(charged);
+            lot.storage_usage += charged;
+        } else {
+            // bytes not charged to the lot are not returned
+            let released = std::cmp::min(storage_usage_before - storage_usage, lot.storage_usage);
+            account.release(released);
+            lot.storage_usage -= released;
+        }
+        self.storage_accounts.insert(&payer_id, &account);
+        self.internal_lot_save(&lot);
+    }
+
+    // Moves the storage charged for the lot from the previous payer to the current one
+    pub(crate) fn internal_storage_transfer_lot(&mut self, lot: &Lot, prev_payer_id: &ProfileId) {
+        if lot.storage_usage == 0 {
+            return;
+        }
+        self.internal_storage_release(prev_payer_id, lot.storage_usage);
+        let payer_id = lot.storage_payer_id();
+        let mut account = self
+            .storage_accounts
+            .get(payer_id)
+            .expect(ERR_STORAGE_NOT_REGISTERED);
+        account.charge(lot.storage_usage);
+        self.storage_accounts.insert(payer_id, &account);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // NEP-145, registers the account on the first deposit, the registration record is paid
+    // from the deposit. registration_only: refund everything above the min
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let deposit = env::attached_deposit();
+
+        let account = match self.storage_accounts.get(&account_id) {
+            Some(mut account) => {
+                if registration_only.unwrap_or(false) && deposit > 0 {
+                    Promise::new(env::predecessor_account_id()).transfer(deposit);
+                    return (&account).into();
+                }
+                account.deposit(deposit);
+                account
+            }
+            None => {
+                assert!(
+                    deposit >= STORAGE_BALANCE_MIN,
+                    "{}",
+                    ERR_STORAGE_DEPOSIT_TOO_SMALL,
+                );
+                let mut account = StorageAccount::default();
+                if registration_only.unwrap_or(false) {
+                    account.deposit(STORAGE_BALANCE_MIN);
+                    if deposit > STORAGE_BALANCE_MIN {
+                        Promise::new(env::predecessor_account_id())
+                            .transfer(deposit - STORAGE_BALANCE_MIN);
+                    }
+                } else {
+                    account.deposit(deposit);
+                }
+                account
+            }
+        };
+
+        let storage_usage = env::storage_usage();
+        self.storage_accounts.insert(&account_id, &account);
+        self.internal_storage_charge(&account_id, storage_usage);
+        let account = self.storage_accounts.get(&account_id).unwrap();
+
+        log!(
+            "storage_deposit: {} deposited {}, total {}",
+            account_id,
+            deposit,
+            account.total()
+        );
+
+        (&account).into()
+    }
+
+    // NEP-145, withdraws the amount or all available balance, requires exactly one yocto
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<WrappedBalance>) -> StorageBalance {
+        near_sdk::assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut account = self
+            .storage_accounts
+            .get(&account_id)
+            .expect(ERR_STORAGE_NOT_REGISTERED);
+        let amount: Balance = amount
+            .map(|x| x.into())
+            .unwrap_or_else(|| account.available());
+        account.withdraw(amount);
+        self.storage_accounts.insert(&account_id, &account);
+
+        if amount > 0 {
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+        log!("storage_withdraw: {} withdrew {}", account_id, amount);
+
+        (&account).into()
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts.get(&account_id).map(|x| (&x).into())
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: STORAGE_BALANCE_MIN.into(),
+            max: None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::tests::*;
+
+    use crate::api_lot::tests::*;
+    use crate::lot::tests::*;
+
+    pub fn api_storage_deposit(contract: &mut Contract, account_id: &AccountId) {
+        testing_env!(get_context_pay(to_ts(10), account_id, to_yocto("1")));
+        contract.storage_deposit(None, None);
+    }
+
+    fn check_storage_not_negative(contract: &Contract, account_id: &AccountId) {
+        let account = contract.storage_accounts.get(account_id).unwrap();
+        assert!(
+            account.used_balance() <= account.total(),
+            "storage balance of {} went negative",
+            account_id,
+        );
+        let balance = contract.storage_balance_of(account_id.clone()).unwrap();
+        assert_eq!(
+            balance.available.0,
+            account.total() - account.used_balance(),
+            "wrong available",
+        );
+    }
+
+    #[test]
+    fn test_api_storage_deposit() {
+        let mut contract = build_contract();
+        let alice: AccountId = "alice".parse().unwrap();
+        let bob: AccountId = "bob".parse().unwrap();
+        assert!(contract.storage_balance_of(alice.clone()).is_none());
+
+        testing_env!(get_context_pay(to_ts(10), &bob, to_yocto("1")));
+        let balance = contract.storage_deposit(Some(alice.clone()), None);
+        assert_eq!(balance.total.0, to_yocto("1"), "wrong total");
+        assert!(
+            balance.available.0 < to_yocto("1"),
+            "expected registration to be charged",
+        );
+        assert_eq!(contract.storage_balance_of(alice.clone()), Some(balance));
+        check_storage_not_negative(&contract, &alice);
+
+        testing_env!(get_context_pay(to_ts(11), &alice, to_yocto("2")));
+        let balance = contract.storage_deposit(None, None);
+        assert_eq!(balance.total.0, to_yocto("3"), "wrong total");
+        check_storage_not_negative(&contract, &alice);
+    }
+
+    #[test]
+    fn test_api_storage_deposit_registration_only() {
+        let mut contract = build_contract();
+        let alice: AccountId = "alice".parse().unwrap();
+
+        testing_env!(get_context_pay(to_ts(10), &alice, to_yocto("1")));
+        let balance = contract.storage_deposit(None, Some(true));
+        assert_eq!(balance.total.0, STORAGE_BALANCE_MIN, "wrong total");
+
+        testing_env!(get_context_pay(to_ts(11), &alice, to_yocto("1")));
+        let balance = contract.storage_deposit(None, Some(true));
+        assert_eq!(
+            balance.total.0, STORAGE_BALANCE_MIN,
+            "expected deposit refunded"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "storage_deposit: deposit is less than the min")]
+    fn test_api_storage_deposit_fail_too_small() {
+        let mut contract = build_contract();
+        let alice: AccountId = "alice".parse().unwrap();
+
+        testing_env!(get_context_pay(to_ts(10), &alice, STORAGE_BALANCE_MIN - 1));
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn test_api_storage_balance_bounds() {
+        let contract = build_contract();
+        let bounds = contract.storage_balance_bounds();
+        assert_eq!(bounds.min.0, STORAGE_BALANCE_MIN, "wrong min");
+        assert!(bounds.max.is_none(), "expected no max");
+    }
+
+    #[test]
+    fn test_api_storage_withdraw() {
+        let mut contract = build_contract();
+        let alice: AccountId = "alice".parse().unwrap();
+        api_storage_deposit(&mut contract, &alice);
+
+        testing_env!(get_context_pay(to_ts(11), &alice, 1));
+        let balance = contract.storage_withdraw(Some(to_yocto("0.5").into()));
+        assert_eq!(balance.total.0, to_yocto("0.5"), "wrong total");
+
+        testing_env!(get_context_pay(to_ts(12), &alice, 1));
+        let balance = contract.storage_withdraw(None);
+        assert_eq!(
+            balance.available.0, 0,
+            "expected everything available withdrawn"
+        );
+        check_storage_not_negative(&contract, &alice);
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: withdraw exceeds available balance")]
+    fn test_api_storage_withdraw_fail_too_much() {
+        let mut contract = build_contract();
+        let alice: AccountId = "alice".parse().unwrap();
+        api_storage_deposit(&mut contract, &alice);
+
+        testing_env!(get_context_pay(to_ts(11), &alice, 1));
+        contract.storage_withdraw(Some(to_yocto("1").into()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_api_storage_withdraw_fail_no_yocto() {
+        let mut contract = build_contract();
+        let alice: AccountId = "alice".parse().unwrap();
+        api_storage_deposit(&mut contract, &alice);
+
+        testing_env!(get_context_call(to_ts(11), &alice));
+        contract.storage_withdraw(None);
+    }
+
+    #[test]
+    fn test_api_storage_charged_by_offer_and_bids() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+
+        api_storage_deposit(&mut contract, &bob);
+        let used_before_offer = contract.storage_accounts.get(&bob).unwrap().used();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        let used_after_offer = contract.storage_accounts.get(&bob).unwrap().used();
+        check_storage_not_negative(&contract, &bob);
+        assert_eq!(
+            contract.lots.get(&alice).unwrap().storage_usage,
+            used_after_offer - used_before_offer,
+            "expected offer charged to the seller",
+        );
+        assert!(
+            contract.storage_accounts.get(&alice).is_none(),
+            "expected lot account not charged",
+        );
+
+        let (lot, _) = create_lot_alice_with_bids();
+        for bid in lot.bids().iter() {
+            api_lot_bid(&mut contract, &alice, bid);
+        }
+        for account_id in [&bob, &carol, &dan] {
+            check_storage_not_negative(&contract, account_id);
+        }
+        assert!(
+            contract.storage_accounts.get(&carol).unwrap().used() > 0,
+            "expected bids to be charged to the bidder",
+        );
+        assert_eq!(
+            contract.storage_accounts.get(&bob).unwrap().used(),
+            used_after_offer,
+            "expected bids not charged to the seller",
+        );
+    }
+
+    #[test]
+    fn test_api_storage_transfer_claim_charged_to_caller() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        let (lot, _) = create_lot_alice_with_bids();
+        for bid in lot.bids().iter() {
+            api_lot_bid(&mut contract, &alice, bid);
+        }
+        let used_seller = contract.storage_accounts.get(&bob).unwrap().used();
+        let storage_usage_lot = contract.lots.get(&alice).unwrap().storage_usage;
+
+        testing_env!(get_context_call(to_ts(18), &dan));
+        contract.lot_transfer_claim(alice.clone(), eve);
+        check_storage_not_negative(&contract, &dan);
+        assert_eq!(
+            contract.storage_accounts.get(&bob).unwrap().used(),
+            used_seller,
+            "expected seller not charged",
+        );
+        assert_eq!(
+            contract.lots.get(&alice).unwrap().storage_usage,
+            storage_usage_lot,
+            "expected lot storage unchanged",
+        );
+    }
+
+    #[test]
+    fn test_api_storage_reoffer_by_winner_moved_to_winner() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        api_storage_deposit(&mut contract, &bob);
+        api_storage_deposit(&mut contract, &dan);
+        let used_seller_before = contract.storage_accounts.get(&bob).unwrap().used();
+        let used_winner_before = contract.storage_accounts.get(&dan).unwrap().used();
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        let (lot, _) = create_lot_alice_with_bids();
+        for bid in lot.bids().iter() {
+            api_lot_bid(&mut contract, &alice, bid);
+        }
+
+        testing_env!(get_context_call(to_ts(18), &dan));
+        contract.lot_reoffer(
+            alice.clone(),
+            to_yocto("7").into(),
+            to_yocto("20").into(),
+            Some(to_ts(30).into()),
+            None,
+            None,
+        );
+        assert_eq!(
+            contract.storage_accounts.get(&bob).unwrap().used(),
+            used_seller_before,
+            "expected lot storage released to the previous seller",
+        );
+        let lot = contract.lots.get(&alice).unwrap();
+        assert_eq!(lot.seller_id, dan, "expected lot relisted");
+        assert!(lot.storage_usage > 0, "expected lot storage charged");
+        // bids of the previous sale are released, only the lot is charged to the winner
+        assert_eq!(
+            contract.storage_accounts.get(&dan).unwrap().used(),
+            used_winner_before + lot.storage_usage,
+            "expected lot storage charged to the winner",
+        );
+        check_storage_not_negative(&contract, &dan);
+    }
+
+    #[test]
+    fn test_api_storage_released_by_bid_retract() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        create_lot_x_sells_y_api(&mut contract, &"bob".parse().unwrap(), &alice);
+        api_storage_deposit(&mut contract, &dan);
+        let used_before_bid = contract.storage_accounts.get(&dan).unwrap().used();
+
+        let (lot, _) = create_lot_alice_with_bids();
+        for bid in lot.bids().iter() {
+            api_lot_bid(&mut contract, &alice, bid);
+        }
+        assert!(
+            contract.storage_accounts.get(&dan).unwrap().used() > used_before_bid,
+            "expected bid charged to the bidder",
+        );
+
+        testing_env!(get_context_call(to_ts(12), &dan));
+        contract.lot_bid_retract(alice);
+        assert_eq!(
+            contract.storage_accounts.get(&dan).unwrap().used(),
+            used_before_bid,
+            "expected retracted bid released",
+        );
+        check_storage_not_negative(&contract, &dan);
+    }
+
+    #[test]
+    fn test_api_storage_released_by_clean_up() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        let bob: ProfileId = "bob".parse().unwrap();
+        let dan: ProfileId = "dan".parse().unwrap();
+        api_storage_deposit(&mut contract, &bob);
+        api_storage_deposit(&mut contract, &carol);
+        api_storage_deposit(&mut contract, &dan);
+        let used_before: Vec<StorageUsage> = [&bob, &carol, &dan]
+            .iter()
+            .map(|x| contract.storage_accounts.get(x).unwrap().used())
+            .collect();
+
+        create_lot_x_sells_y_api(&mut contract, &bob, &alice);
+        let (lot, _) = create_lot_alice_with_bids();
+        for bid in lot.bids().iter() {
+            api_lot_bid(&mut contract, &alice, bid);
+        }
+
+        let lot = contract.internal_lot_extract(&alice);
+        let result = contract.internal_lot_clean_up(lot, LOT_CLEAN_UP_STEP_MAX_ITEMS);
+        assert_eq!(result, true, "expected clean up finished");
+        for (account_id, used) in [&bob, &carol, &dan].iter().zip(used_before) {
+            assert_eq!(
+                contract.storage_accounts.get(account_id).unwrap().used(),
+                used,
+                "expected storage of {} released",
+                account_id,
+            );
+            check_storage_not_negative(&contract, account_id);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: account is not registered")]
+    fn test_api_storage_lot_offer_fail_not_registered() {
+        let mut contract = build_contract();
+
+        testing_env!(get_context_call(to_ts(10), &"alice".parse().unwrap()));
+        contract.lot_offer(
+            "bob".parse().unwrap(),
+            to_yocto("2").into(),
+            to_yocto("10").into(),
+            None,
+            Some(to_nanos(7).into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: not enough storage balance")]
+    fn test_api_storage_lot_bid_fail_not_enough_balance() {
+        let mut contract = build_contract();
+        let alice: LotId = "alice".parse().unwrap();
+        let carol: ProfileId = "carol".parse().unwrap();
+        create_lot_x_sells_y_api(&mut contract, &"bob".parse().unwrap(), &alice);
+
+        testing_env!(get_context_pay(to_ts(10), &carol, STORAGE_BALANCE_MIN));
+        contract.storage_deposit(None, None);
+        testing_env!(get_context_pay(to_ts(10), &carol, 1));
+        let balance = contract.storage_withdraw(None);
+        assert_eq!(balance.available.0, 0);
+
+        testing_env!(get_context_pay(to_ts(11), &carol, to_yocto("6")));
+        contract.lot_bid(alice, None, None, None);
+    }
+}
@@ -2,22 +2,50 @@ use crate::*;
 
 pub const ERR_PROFILE_INTERNAL_SAVE_ALREADY_EXISTS: &str =
     "internal_profile_save: profile already exists";
+pub const ERR_NEW_PREV_BIDDER_COMMISSION_SHARE_OUT_OF_BOUNDS: &str =
+    "new: expected prev bidder commission share within min and max";
+pub const ERR_NEW_BID_STEP_OUT_OF_BOUNDS: &str = "new: expected bid step within min and max";
 
 pub const LOT_OFFER_MIN_RESERVE_PRICE: Balance = 500 * 10u128.pow(21);
 pub const LOT_OFFER_MAX_DURATION: Duration = 90 * 24 * 60 * 60 * 10u64.pow(9);
 pub const LOT_REMOVE_UNSAFE_GRACE_DURATION: Duration = 2 * 60 * 60 * 10u64.pow(9);
+pub const LOT_CLAIM_PENDING_GRACE_DURATION: Duration = 10 * 60 * 10u64.pow(9);
+pub const LOT_CLAIM_SWEEP_KEEP_AMOUNT: Balance = 100 * 10u128.pow(21);
+pub const LOT_PAYOUTS_MAX_LEN: u64 = 5;
+pub const LOT_BID_RETRACT_DURATION: Duration = 5 * 60 * 10u64.pow(9);
+pub const LOT_RELIST_MAX_ROUNDS: u32 = 10;
+pub const LOT_BUNDLE_MAX_LEN: u64 = 4;
+pub const LOT_RAFFLE_MAX_TICKETS: u64 = 100;
+pub const STORAGE_BALANCE_MIN: Balance = 10 * 10u128.pow(21);
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     profiles: UnorderedMap<ProfileId, Profile>,
     pub lots: UnorderedMap<LotId, Lot>,
+    // bundled account -> lot it is sold with
+    pub lot_bundle_members: UnorderedMap<LotId, LotId>,
+    // NEP-145 storage balances, charged by lot_offer and lot_bid
+    pub storage_accounts: UnorderedMap<AccountId, StorageAccount>,
     pub seller_rewards_commission: Fraction,
     pub bid_step: Fraction,
+    // bounds for the bid step sellers may choose per lot
+    pub bid_step_min: Fraction,
+    pub bid_step_max: Fraction,
+    pub bid_step_min_increment_max: Balance,
     pub prev_bidder_commission_share: Fraction,
+    // bounds for the share sellers may choose per lot
+    pub prev_bidder_commission_share_min: Fraction,
+    pub prev_bidder_commission_share_max: Fraction,
+    pub referrer_commission_share: Fraction,
+    pub commission_schedule: Vec<(Balance, Fraction)>,
+    // part of the retracted bid kept as the seller reward
+    pub bid_retract_penalty: Fraction,
+    // part of the top bid paid by the seller to the top bidder on lot cancellation
+    pub lot_cancel_penalty: Fraction,
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FractionView {
     pub num: u32,
@@ -38,10 +66,27 @@ impl From<&Fraction> for FractionView {
 pub struct ContractConfigView {
     pub seller_rewards_commission: FractionView,
     pub bid_step: FractionView,
+    pub bid_step_min: FractionView,
+    pub bid_step_max: FractionView,
+    pub bid_step_min_increment_max: WrappedBalance,
     pub prev_bidder_commission_share: FractionView,
+    pub prev_bidder_commission_share_min: FractionView,
+    pub prev_bidder_commission_share_max: FractionView,
+    pub referrer_commission_share: FractionView,
+    pub commission_schedule: Vec<(WrappedBalance, FractionView)>,
+    pub bid_retract_penalty: FractionView,
+    pub lot_cancel_penalty: FractionView,
     pub lot_offer_min_reserve_price: WrappedBalance,
     pub lot_offer_max_duration: WrappedDuration,
     pub lot_remove_unsafe_grace_duration: WrappedDuration,
+    pub lot_claim_pending_grace_duration: WrappedDuration,
+    pub lot_claim_sweep_keep_amount: WrappedBalance,
+    pub lot_payouts_max_len: u64,
+    pub lot_bid_retract_duration: WrappedDuration,
+    pub lot_relist_max_rounds: u32,
+    pub lot_bundle_max_len: u64,
+    pub lot_raffle_max_tickets: u64,
+    pub storage_balance_min: WrappedBalance,
 }
 
 impl From<&Contract> for ContractConfigView {
@@ -49,10 +94,31 @@ impl From<&Contract> for ContractConfigView {
         ContractConfigView {
             seller_rewards_commission: (&contract.seller_rewards_commission).into(),
             bid_step: (&contract.bid_step).into(),
+            bid_step_min: (&contract.bid_step_min).into(),
+            bid_step_max: (&contract.bid_step_max).into(),
+            bid_step_min_increment_max: contract.bid_step_min_increment_max.into(),
             prev_bidder_commission_share: (&contract.prev_bidder_commission_share).into(),
+            prev_bidder_commission_share_min: (&contract.prev_bidder_commission_share_min).into(),
+            prev_bidder_commission_share_max: (&contract.prev_bidder_commission_share_max).into(),
+            referrer_commission_share: (&contract.referrer_commission_share).into(),
+            commission_schedule: contract
+                .commission_schedule
+                .iter()
+                .map(|(threshold, rate)| ((*threshold).into(), rate.into()))
+                .collect(),
+            bid_retract_penalty: (&contract.bid_retract_penalty).into(),
+            lot_cancel_penalty: (&contract.lot_cancel_penalty).into(),
             lot_offer_min_reserve_price: LOT_OFFER_MIN_RESERVE_PRICE.into(),
             lot_offer_max_duration: LOT_OFFER_MAX_DURATION.into(),
             lot_remove_unsafe_grace_duration: LOT_REMOVE_UNSAFE_GRACE_DURATION.into(),
+            lot_claim_pending_grace_duration: LOT_CLAIM_PENDING_GRACE_DURATION.into(),
+            lot_claim_sweep_keep_amount: LOT_CLAIM_SWEEP_KEEP_AMOUNT.into(),
+            lot_payouts_max_len: LOT_PAYOUTS_MAX_LEN,
+            lot_bid_retract_duration: LOT_BID_RETRACT_DURATION.into(),
+            lot_relist_max_rounds: LOT_RELIST_MAX_ROUNDS,
+            lot_bundle_max_len: LOT_BUNDLE_MAX_LEN,
+            lot_raffle_max_tickets: LOT_RAFFLE_MAX_TICKETS,
+            storage_balance_min: STORAGE_BALANCE_MIN.into(),
         }
     }
 }
@@ -64,23 +130,88 @@ impl Contract {
     }
 
     #[init(ignore_state)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         seller_rewards_commission: FractionView,
         bid_step: FractionView,
         prev_bidder_commission_share: FractionView,
+        referrer_commission_share: Option<FractionView>,
+        commission_schedule: Option<Vec<(WrappedBalance, FractionView)>>,
+        prev_bidder_commission_share_min: Option<FractionView>,
+        prev_bidder_commission_share_max: Option<FractionView>,
+        bid_step_min: Option<FractionView>,
+        bid_step_max: Option<FractionView>,
+        bid_step_min_increment_max: Option<WrappedBalance>,
+        bid_retract_penalty: Option<FractionView>,
+        lot_cancel_penalty: Option<FractionView>,
     ) -> Self {
+        let referrer_commission_share =
+            referrer_commission_share.unwrap_or(FractionView { num: 0, denom: 1 });
+        let commission_schedule: Vec<(Balance, Fraction)> = commission_schedule
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(threshold, rate)| (threshold.into(), Fraction::new(rate.num, rate.denom)))
+            .collect();
+        validate_commission_schedule(&commission_schedule);
+
+        let prev_bidder_commission_share = Fraction::new(
+            prev_bidder_commission_share.num,
+            prev_bidder_commission_share.denom,
+        );
+        let prev_bidder_commission_share_min = prev_bidder_commission_share_min
+            .map(|x| Fraction::new(x.num, x.denom))
+            .unwrap_or_else(|| Fraction::new(0, 1));
+        let prev_bidder_commission_share_max = prev_bidder_commission_share_max
+            .map(|x| Fraction::new(x.num, x.denom))
+            .unwrap_or_else(|| Fraction::new(1, 1));
+        assert!(
+            prev_bidder_commission_share_min <= prev_bidder_commission_share
+                && prev_bidder_commission_share <= prev_bidder_commission_share_max,
+            "{}",
+            ERR_NEW_PREV_BIDDER_COMMISSION_SHARE_OUT_OF_BOUNDS,
+        );
+
+        let bid_step = Fraction::new(bid_step.num, bid_step.denom);
+        let bid_step_min = bid_step_min
+            .map(|x| Fraction::new(x.num, x.denom))
+            .unwrap_or_else(|| Fraction::new(0, 1));
+        let bid_step_max = bid_step_max
+            .map(|x| Fraction::new(x.num, x.denom))
+            .unwrap_or_else(|| Fraction::new(1, 1));
+        assert!(
+            bid_step_min <= bid_step && bid_step <= bid_step_max,
+            "{}",
+            ERR_NEW_BID_STEP_OUT_OF_BOUNDS,
+        );
+        let bid_step_min_increment_max: Balance = bid_step_min_increment_max
+            .map(|x| x.into())
+            .unwrap_or(Balance::MAX);
+        let bid_retract_penalty = bid_retract_penalty.unwrap_or(FractionView { num: 1, denom: 10 });
+        let lot_cancel_penalty = lot_cancel_penalty.unwrap_or(FractionView { num: 1, denom: 10 });
+
         Self {
             profiles: UnorderedMap::new(PREFIX_PROFILES.as_bytes().to_vec()),
             lots: UnorderedMap::new(PREFIX_LOTS.as_bytes().to_vec()),
+            lot_bundle_members: UnorderedMap::new(PREFIX_LOT_BUNDLE_MEMBERS.as_bytes().to_vec()),
+            storage_accounts: UnorderedMap::new(PREFIX_STORAGE_ACCOUNTS.as_bytes().to_vec()),
             seller_rewards_commission: Fraction::new(
                 seller_rewards_commission.num,
                 seller_rewards_commission.denom,
             ),
-            bid_step: Fraction::new(bid_step.num, bid_step.denom),
-            prev_bidder_commission_share: Fraction::new(
-                prev_bidder_commission_share.num,
-                prev_bidder_commission_share.denom,
+            bid_step,
+            bid_step_min,
+            bid_step_max,
+            bid_step_min_increment_max,
+            prev_bidder_commission_share,
+            prev_bidder_commission_share_min,
+            prev_bidder_commission_share_max,
+            referrer_commission_share: Fraction::new(
+                referrer_commission_share.num,
+                referrer_commission_share.denom,
             ),
+            commission_schedule,
+            bid_retract_penalty: Fraction::new(bid_retract_penalty.num, bid_retract_penalty.denom),
+            lot_cancel_penalty: Fraction::new(lot_cancel_penalty.num, lot_cancel_penalty.denom),
         }
     }
 }
@@ -131,11 +262,45 @@ mod tests {
             FractionView { num: 1, denom: 5 },
             "wrong bid_step",
         );
+        assert_eq!(
+            config.bid_step_min,
+            FractionView { num: 1, denom: 10 },
+            "wrong bid step min",
+        );
+        assert_eq!(
+            config.bid_step_max,
+            FractionView { num: 1, denom: 2 },
+            "wrong bid step max",
+        );
+        assert_eq!(
+            config.bid_step_min_increment_max,
+            to_yocto("1").into(),
+            "wrong bid step min increment max",
+        );
         assert_eq!(
             config.prev_bidder_commission_share,
             FractionView { num: 4, denom: 5 },
             "wrong seller rewards commission",
         );
+        assert_eq!(
+            config.prev_bidder_commission_share_min,
+            FractionView { num: 1, denom: 10 },
+            "wrong prev bidder commission share min",
+        );
+        assert_eq!(
+            config.prev_bidder_commission_share_max,
+            FractionView { num: 9, denom: 10 },
+            "wrong prev bidder commission share max",
+        );
+        assert_eq!(
+            config.referrer_commission_share,
+            FractionView { num: 1, denom: 2 },
+            "wrong referrer commission share",
+        );
+        assert!(
+            config.commission_schedule.is_empty(),
+            "expected empty commission schedule",
+        );
         assert_eq!(
             config.lot_offer_min_reserve_price,
             LOT_OFFER_MIN_RESERVE_PRICE.into(),
@@ -151,6 +316,133 @@ mod tests {
             LOT_REMOVE_UNSAFE_GRACE_DURATION.into(),
             "wrong grace_duration",
         );
+        assert_eq!(
+            config.lot_claim_pending_grace_duration,
+            LOT_CLAIM_PENDING_GRACE_DURATION.into(),
+            "wrong claim pending grace duration",
+        );
+        assert_eq!(
+            config.lot_claim_sweep_keep_amount,
+            LOT_CLAIM_SWEEP_KEEP_AMOUNT.into(),
+            "wrong sweep keep amount",
+        );
+        assert_eq!(
+            config.lot_payouts_max_len, LOT_PAYOUTS_MAX_LEN,
+            "wrong payouts max len",
+        );
+        assert_eq!(
+            config.bid_retract_penalty,
+            FractionView { num: 1, denom: 10 },
+            "wrong bid retract penalty",
+        );
+        assert_eq!(
+            config.lot_cancel_penalty,
+            FractionView { num: 1, denom: 10 },
+            "wrong lot cancel penalty",
+        );
+        assert_eq!(
+            config.lot_bid_retract_duration,
+            LOT_BID_RETRACT_DURATION.into(),
+            "wrong bid retract duration",
+        );
+        assert_eq!(
+            config.lot_relist_max_rounds, LOT_RELIST_MAX_ROUNDS,
+            "wrong relist max rounds",
+        );
+        assert_eq!(
+            config.lot_bundle_max_len, LOT_BUNDLE_MAX_LEN,
+            "wrong bundle max len",
+        );
+        assert_eq!(
+            config.lot_raffle_max_tickets, LOT_RAFFLE_MAX_TICKETS,
+            "wrong raffle max tickets",
+        );
+        assert_eq!(
+            config.storage_balance_min,
+            STORAGE_BALANCE_MIN.into(),
+            "wrong storage balance min",
+        );
+    }
+
+    fn build_contract_with_commission_schedule(
+        commission_schedule: Vec<(WrappedBalance, FractionView)>,
+    ) -> Contract {
+        Contract::new(
+            FractionView { num: 1, denom: 10 },
+            FractionView { num: 1, denom: 5 },
+            FractionView { num: 4, denom: 5 },
+            None,
+            Some(commission_schedule),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn contract_config_get_commission_schedule() {
+        let schedule = vec![
+            (to_yocto("10").into(), FractionView { num: 1, denom: 20 }),
+            (to_yocto("100").into(), FractionView { num: 1, denom: 100 }),
+        ];
+        let contract = build_contract_with_commission_schedule(schedule.clone());
+
+        let config = contract.config_get();
+        assert_eq!(
+            config.commission_schedule, schedule,
+            "wrong commission schedule",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "commission schedule: expected increasing positive thresholds")]
+    fn contract_new_fail_commission_schedule_not_increasing() {
+        build_contract_with_commission_schedule(vec![
+            (to_yocto("100").into(), FractionView { num: 1, denom: 20 }),
+            (to_yocto("10").into(), FractionView { num: 1, denom: 100 }),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "new: expected prev bidder commission share within min and max")]
+    fn contract_new_fail_prev_bidder_commission_share_out_of_bounds() {
+        Contract::new(
+            FractionView { num: 1, denom: 10 },
+            FractionView { num: 1, denom: 5 },
+            FractionView { num: 4, denom: 5 },
+            None,
+            None,
+            Some(FractionView { num: 1, denom: 10 }),
+            Some(FractionView { num: 1, denom: 2 }),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "new: expected bid step within min and max")]
+    fn contract_new_fail_bid_step_out_of_bounds() {
+        Contract::new(
+            FractionView { num: 1, denom: 10 },
+            FractionView { num: 1, denom: 5 },
+            FractionView { num: 4, denom: 5 },
+            None,
+            None,
+            None,
+            None,
+            Some(FractionView { num: 1, denom: 4 }),
+            None,
+            None,
+            None,
+            None,
+        );
     }
 }
 
@@ -1,29 +1,135 @@
 use crate::*;
 
+pub const ERR_PAYOUTS_EMPTY: &str = "payouts: expected at least one beneficiary";
+pub const ERR_PAYOUTS_TOO_MANY: &str = "payouts: too many beneficiaries";
+pub const ERR_PAYOUTS_DUPLICATE: &str = "payouts: duplicate beneficiary";
+pub const ERR_PAYOUTS_SUM_NOT_ONE: &str = "payouts: expected shares sum to one";
+pub const ERR_COMMISSION_SCHEDULE_THRESHOLDS: &str =
+    "commission schedule: expected increasing positive thresholds";
+
+pub fn validate_commission_schedule(commission_schedule: &[(Balance, Fraction)]) {
+    let mut prev_threshold: Balance = 0;
+    for (threshold, _) in commission_schedule {
+        assert!(
+            *threshold > prev_threshold,
+            "{}",
+            ERR_COMMISSION_SCHEDULE_THRESHOLDS,
+        );
+        prev_threshold = *threshold;
+    }
+}
+
+// Commission on the bid increment [prev_amount, amount), applied marginally like tax brackets:
+// seller_rewards_commission below the first threshold, each schedule rate above its threshold
+pub fn calc_commission(
+    prev_amount: Balance,
+    amount: Balance,
+    seller_rewards_commission: Fraction,
+    commission_schedule: &[(Balance, Fraction)],
+) -> Balance {
+    let brackets: Vec<(Balance, Fraction)> = std::iter::once((0, seller_rewards_commission))
+        .chain(commission_schedule.iter().copied())
+        .collect();
+
+    brackets
+        .iter()
+        .enumerate()
+        .map(|(idx, (threshold, rate))| {
+            let next_threshold = brackets.get(idx + 1).map(|x| x.0).unwrap_or(Balance::MAX);
+            let from = std::cmp::max(prev_amount, *threshold);
+            let to = std::cmp::min(amount, next_threshold);
+            if from < to {
+                *rate * (to - from)
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+// referrer reward is taken from the commission left after the prev bidder reward
 pub fn calc_lot_bid_rewards(
     prev_amount: Option<Balance>,
     amount: Balance,
     seller_rewards_commission: Fraction,
+    commission_schedule: &[(Balance, Fraction)],
     prev_bidder_commission_share: Fraction,
-) -> (Option<Balance>, Balance) {
-    match prev_amount {
+    referrer_commission_share: Option<Fraction>,
+) -> (Option<Balance>, Balance, Balance) {
+    let (to_prev_bidder, to_seller, commission_left) = match prev_amount {
         Some(prev_amount) => {
             let to_prev_bidder_bid = prev_amount;
             let to_seller = amount - to_prev_bidder_bid;
-            let commission = seller_rewards_commission * to_seller;
+            let commission = calc_commission(
+                prev_amount,
+                amount,
+                seller_rewards_commission,
+                commission_schedule,
+            );
             let to_seller = to_seller - commission;
             let to_prev_bidder_reward = prev_bidder_commission_share * commission;
             let to_prev_bidder = to_prev_bidder_bid + to_prev_bidder_reward;
 
-            (Some(to_prev_bidder), to_seller)
+            (
+                Some(to_prev_bidder),
+                to_seller,
+                commission - to_prev_bidder_reward,
+            )
         }
         None => {
             let to_seller = amount;
-            let commission = seller_rewards_commission * to_seller;
+            let commission =
+                calc_commission(0, amount, seller_rewards_commission, commission_schedule);
             let to_seller = to_seller - commission;
-            (None, to_seller)
+            (None, to_seller, commission)
         }
+    };
+    let to_referrer = referrer_commission_share
+        .map(|share| std::cmp::min(share * commission_left, commission_left))
+        .unwrap_or(0);
+
+    (to_prev_bidder, to_seller, to_referrer)
+}
+
+pub fn validate_payouts(payouts: &[(ProfileId, Fraction)]) {
+    assert!(!payouts.is_empty(), "{}", ERR_PAYOUTS_EMPTY);
+    assert!(
+        payouts.len() as u64 <= LOT_PAYOUTS_MAX_LEN,
+        "{}",
+        ERR_PAYOUTS_TOO_MANY,
+    );
+    let beneficiary_ids_unique: HashSet<&ProfileId> = payouts.iter().map(|x| &x.0).collect();
+    assert_eq!(
+        beneficiary_ids_unique.len(),
+        payouts.len(),
+        "{}",
+        ERR_PAYOUTS_DUPLICATE,
+    );
+
+    // exact sum without rounding, denominators product fits into U256
+    let mut num = U256::zero();
+    let mut denom = U256::one();
+    for (_, share) in payouts {
+        num = num * U256::from(share.denom()) + U256::from(share.num()) * denom;
+        denom *= U256::from(share.denom());
     }
+    assert_eq!(num, denom, "{}", ERR_PAYOUTS_SUM_NOT_ONE);
+}
+
+// first beneficiary is the primary one and receives the rounding dust
+pub fn calc_payouts(
+    amount: Balance,
+    payouts: &[(ProfileId, Fraction)],
+) -> Vec<(ProfileId, Balance)> {
+    let mut result: Vec<(ProfileId, Balance)> = payouts
+        .iter()
+        .skip(1)
+        .map(|(beneficiary_id, share)| (beneficiary_id.clone(), *share * amount))
+        .collect();
+    let to_primary = amount - result.iter().map(|x| x.1).sum::<Balance>();
+    result.insert(0, (payouts[0].0.clone(), to_primary));
+
+    result
 }
 
 #[cfg(test)]
@@ -34,14 +140,17 @@ mod tests {
     fn test_economics_calc_lot_bid_rewards_zero_commissions() {
         let z = Fraction::new(0, 1);
 
-        let (to_prev_bidder, to_seller) = calc_lot_bid_rewards(None, to_yocto("10"), z, z);
+        let (to_prev_bidder, to_seller, to_referrer) =
+            calc_lot_bid_rewards(None, to_yocto("10"), z, &[], z, Some(z));
         assert_eq!(to_prev_bidder, None);
         assert_eq!(to_seller, to_yocto("10"));
+        assert_eq!(to_referrer, 0);
 
-        let (to_prev_bidder, to_seller) =
-            calc_lot_bid_rewards(Some(to_yocto("10")), to_yocto("15"), z, z);
+        let (to_prev_bidder, to_seller, to_referrer) =
+            calc_lot_bid_rewards(Some(to_yocto("10")), to_yocto("15"), z, &[], z, Some(z));
         assert_eq!(to_prev_bidder, Some(to_yocto("10")));
         assert_eq!(to_seller, to_yocto("5"));
+        assert_eq!(to_referrer, 0);
     }
 
     #[test]
@@ -49,13 +158,292 @@ mod tests {
         let c = Fraction::new(1, 10);
         let cs = Fraction::new(4, 5);
 
-        let (to_prev_bidder, to_seller) = calc_lot_bid_rewards(None, to_yocto("10"), c, cs);
+        let (to_prev_bidder, to_seller, to_referrer) =
+            calc_lot_bid_rewards(None, to_yocto("10"), c, &[], cs, None);
+        assert_eq!(to_prev_bidder, None);
+        assert_eq!(to_seller, to_yocto("9"));
+        assert_eq!(to_referrer, 0);
+
+        let (to_prev_bidder, to_seller, to_referrer) =
+            calc_lot_bid_rewards(Some(to_yocto("10")), to_yocto("15"), c, &[], cs, None);
+        assert_eq!(to_prev_bidder, Some(to_yocto("10.4")));
+        assert_eq!(to_seller, to_yocto("4.5"));
+        assert_eq!(to_referrer, 0);
+    }
+
+    #[test]
+    fn test_economics_calc_lot_bid_rewards_referrer() {
+        let c = Fraction::new(1, 10);
+        let cs = Fraction::new(4, 5);
+        let rs = Fraction::new(1, 2);
+
+        let (to_prev_bidder, to_seller, to_referrer) =
+            calc_lot_bid_rewards(None, to_yocto("10"), c, &[], cs, Some(rs));
         assert_eq!(to_prev_bidder, None);
         assert_eq!(to_seller, to_yocto("9"));
+        assert_eq!(to_referrer, to_yocto("0.5"), "expected half of commission");
 
-        let (to_prev_bidder, to_seller) =
-            calc_lot_bid_rewards(Some(to_yocto("10")), to_yocto("15"), c, cs);
+        let (to_prev_bidder, to_seller, to_referrer) =
+            calc_lot_bid_rewards(Some(to_yocto("10")), to_yocto("15"), c, &[], cs, Some(rs));
         assert_eq!(to_prev_bidder, Some(to_yocto("10.4")));
         assert_eq!(to_seller, to_yocto("4.5"));
+        assert_eq!(
+            to_referrer,
+            to_yocto("0.05"),
+            "expected half of commission left after prev bidder reward"
+        );
+
+        let (_, _, to_referrer) = calc_lot_bid_rewards(
+            Some(to_yocto("10")),
+            to_yocto("15"),
+            c,
+            &[],
+            Fraction::new(1, 1),
+            Some(Fraction::new(1, 1)),
+        );
+        assert_eq!(to_referrer, 0, "expected nothing left for referrer");
+    }
+
+    fn create_payouts(shares: &[(&str, u32, u32)]) -> Vec<(ProfileId, Fraction)> {
+        shares
+            .iter()
+            .map(|(id, num, denom)| (id.parse().unwrap(), Fraction::new(*num, *denom)))
+            .collect()
+    }
+
+    #[test]
+    fn test_economics_validate_payouts() {
+        validate_payouts(&create_payouts(&[("bob", 1, 1)]));
+        validate_payouts(&create_payouts(&[("bob", 1, 3), ("carol", 2, 3)]));
+        validate_payouts(&create_payouts(&[
+            ("bob", 1, 2),
+            ("carol", 1, 3),
+            ("dan", 1, 7),
+            ("eve", 1, 42),
+            ("frank", 0, 1),
+        ]));
+    }
+
+    #[test]
+    #[should_panic(expected = "payouts: expected at least one beneficiary")]
+    fn test_economics_validate_payouts_fail_empty() {
+        validate_payouts(&[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "payouts: too many beneficiaries")]
+    fn test_economics_validate_payouts_fail_too_many() {
+        validate_payouts(&create_payouts(&[
+            ("bob", 1, 6),
+            ("carol", 1, 6),
+            ("dan", 1, 6),
+            ("eve", 1, 6),
+            ("frank", 1, 6),
+            ("gina", 1, 6),
+        ]));
+    }
+
+    #[test]
+    #[should_panic(expected = "payouts: duplicate beneficiary")]
+    fn test_economics_validate_payouts_fail_duplicate() {
+        validate_payouts(&create_payouts(&[("bob", 1, 2), ("bob", 1, 2)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "payouts: expected shares sum to one")]
+    fn test_economics_validate_payouts_fail_sum_less_than_one() {
+        validate_payouts(&create_payouts(&[("bob", 1, 3), ("carol", 1, 3)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "payouts: expected shares sum to one")]
+    fn test_economics_validate_payouts_fail_sum_greater_than_one() {
+        validate_payouts(&create_payouts(&[
+            ("bob", 1_000_000_001, 2_000_000_000),
+            ("carol", 1, 2),
+        ]));
+    }
+
+    #[test]
+    fn test_economics_calc_payouts() {
+        let payouts = create_payouts(&[("bob", 1, 3), ("carol", 1, 3), ("dan", 1, 3)]);
+
+        let result = calc_payouts(to_yocto("3"), &payouts);
+        let expected: Vec<(ProfileId, Balance)> = vec![
+            ("bob".parse().unwrap(), to_yocto("1")),
+            ("carol".parse().unwrap(), to_yocto("1")),
+            ("dan".parse().unwrap(), to_yocto("1")),
+        ];
+        assert_eq!(result, expected, "wrong even split");
+
+        let result = calc_payouts(to_yocto("3") + 2, &payouts);
+        let expected: Vec<(ProfileId, Balance)> = vec![
+            ("bob".parse().unwrap(), to_yocto("1") + 2),
+            ("carol".parse().unwrap(), to_yocto("1")),
+            ("dan".parse().unwrap(), to_yocto("1")),
+        ];
+        assert_eq!(result, expected, "expected dust to primary beneficiary");
+
+        let result = calc_payouts(1, &payouts);
+        let expected: Vec<(ProfileId, Balance)> = vec![
+            ("bob".parse().unwrap(), 1),
+            ("carol".parse().unwrap(), 0),
+            ("dan".parse().unwrap(), 0),
+        ];
+        assert_eq!(result, expected, "expected all to primary beneficiary");
+    }
+
+    fn create_commission_schedule() -> Vec<(Balance, Fraction)> {
+        vec![
+            (to_yocto("10"), Fraction::new(1, 20)),
+            (to_yocto("100"), Fraction::new(1, 100)),
+        ]
+    }
+
+    #[test]
+    fn test_economics_validate_commission_schedule() {
+        validate_commission_schedule(&[]);
+        validate_commission_schedule(&create_commission_schedule());
+    }
+
+    #[test]
+    #[should_panic(expected = "commission schedule: expected increasing positive thresholds")]
+    fn test_economics_validate_commission_schedule_fail_not_increasing() {
+        validate_commission_schedule(&[
+            (to_yocto("10"), Fraction::new(1, 20)),
+            (to_yocto("10"), Fraction::new(1, 100)),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "commission schedule: expected increasing positive thresholds")]
+    fn test_economics_validate_commission_schedule_fail_zero_threshold() {
+        validate_commission_schedule(&[(0, Fraction::new(1, 20))]);
+    }
+
+    #[test]
+    fn test_economics_calc_commission() {
+        let c = Fraction::new(1, 10);
+        let schedule = create_commission_schedule();
+
+        assert_eq!(
+            calc_commission(0, to_yocto("15"), c, &[]),
+            to_yocto("1.5"),
+            "expected flat commission without schedule",
+        );
+        assert_eq!(
+            calc_commission(0, to_yocto("8"), c, &schedule),
+            to_yocto("0.8"),
+            "expected base commission below first threshold",
+        );
+        // 10 * 1/10 + 90 * 1/20 + 50 * 1/100
+        assert_eq!(
+            calc_commission(0, to_yocto("150"), c, &schedule),
+            to_yocto("6"),
+            "wrong commission over all tiers",
+        );
+        // 2 * 1/10 + 8 * 1/20
+        assert_eq!(
+            calc_commission(to_yocto("8"), to_yocto("18"), c, &schedule),
+            to_yocto("0.6"),
+            "wrong commission on increment crossing threshold",
+        );
+        assert_eq!(
+            calc_commission(to_yocto("120"), to_yocto("150"), c, &schedule),
+            to_yocto("0.3"),
+            "wrong commission on increment in the last tier",
+        );
+        assert_eq!(
+            calc_commission(to_yocto("8"), to_yocto("8"), c, &schedule),
+            0,
+            "expected zero commission on zero increment",
+        );
+    }
+
+    #[test]
+    fn test_economics_calc_lot_bid_rewards_commission_schedule() {
+        let c = Fraction::new(1, 10);
+        let cs = Fraction::new(4, 5);
+        let schedule = create_commission_schedule();
+
+        // commission 2 * 1/10 + 8 * 1/20 = 0.6
+        let (to_prev_bidder, to_seller, to_referrer) = calc_lot_bid_rewards(
+            Some(to_yocto("8")),
+            to_yocto("18"),
+            c,
+            &schedule,
+            cs,
+            Some(Fraction::new(1, 1)),
+        );
+        assert_eq!(to_prev_bidder, Some(to_yocto("8.48")));
+        assert_eq!(to_seller, to_yocto("9.4"));
+        assert_eq!(to_referrer, to_yocto("0.12"));
+    }
+
+    // deterministic linear congruential generator, no extra dev dependencies
+    fn lcg_next(seed: &mut u64, modulo: u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) % modulo
+    }
+
+    fn lcg_next_fraction(seed: &mut u64) -> Fraction {
+        let denom = lcg_next(seed, 1000) + 1;
+        let num = lcg_next(seed, denom + 1);
+        Fraction::new(num as u32, denom as u32)
+    }
+
+    fn lcg_next_balance(seed: &mut u64) -> Balance {
+        lcg_next(seed, 1_000_000_000) as Balance * 10u128.pow(18) + lcg_next(seed, 1000) as Balance
+    }
+
+    #[test]
+    fn test_economics_calc_lot_bid_rewards_property_no_overpay() {
+        let mut seed: u64 = 42;
+
+        for _ in 0..1000 {
+            let c = lcg_next_fraction(&mut seed);
+            let cs = lcg_next_fraction(&mut seed);
+            let rs = lcg_next_fraction(&mut seed);
+            let mut schedule: Vec<(Balance, Fraction)> = vec![];
+            let mut threshold: Balance = 0;
+            for _ in 0..lcg_next(&mut seed, 4) {
+                threshold += lcg_next_balance(&mut seed) + 1;
+                schedule.push((threshold, lcg_next_fraction(&mut seed)));
+            }
+            validate_commission_schedule(&schedule);
+
+            let prev_amount = lcg_next_balance(&mut seed);
+            let amount = prev_amount + lcg_next_balance(&mut seed);
+            let prev_amount = match lcg_next(&mut seed, 5) {
+                0 => None,
+                _ => Some(prev_amount),
+            };
+            let referrer_share = match lcg_next(&mut seed, 2) {
+                0 => None,
+                _ => Some(rs),
+            };
+
+            let commission = calc_commission(prev_amount.unwrap_or(0), amount, c, &schedule);
+            assert!(
+                commission <= amount - prev_amount.unwrap_or(0),
+                "commission exceeds bid increment"
+            );
+
+            let (to_prev_bidder, to_seller, to_referrer) =
+                calc_lot_bid_rewards(prev_amount, amount, c, &schedule, cs, referrer_share);
+            let total = to_prev_bidder.unwrap_or(0) + to_seller + to_referrer;
+            assert!(
+                total <= amount,
+                "payouts {} exceed deposit {}",
+                total,
+                amount
+            );
+            assert!(
+                to_prev_bidder.unwrap_or(0) >= prev_amount.unwrap_or(0),
+                "expected prev bid to be returned"
+            );
+        }
     }
 }
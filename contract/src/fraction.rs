@@ -28,6 +28,27 @@ impl Fraction {
     }
 }
 
+// compares values, not representations: 1/2 == 2/4
+impl PartialEq for Fraction {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Fraction {}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fraction {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.num as u64 * other.denom as u64).cmp(&(other.num as u64 * self.denom as u64))
+    }
+}
+
 impl ops::Mul<Balance> for Fraction {
     type Output = Balance;
 
@@ -90,4 +111,23 @@ mod tests {
         );
         assert_eq!(Fraction::new(2, 3) * 10, 6, "expected floor rounding");
     }
+
+    #[test]
+    pub fn test_fractions_cmp() {
+        assert_eq!(
+            Fraction::new(1, 2),
+            Fraction::new(2, 4),
+            "expected equal values"
+        );
+        assert_eq!(
+            Fraction::new(0, 1),
+            Fraction::new(0, 7),
+            "expected equal zeros"
+        );
+        assert!(Fraction::new(1, 3) < Fraction::new(1, 2), "expected less");
+        assert!(
+            Fraction::new(u32::MAX, u32::MAX) > Fraction::new(u32::MAX - 1, u32::MAX),
+            "expected no overflow"
+        );
+    }
 }
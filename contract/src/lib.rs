@@ -1,10 +1,12 @@
 mod api_lot;
 mod api_profile;
+mod api_storage;
 mod contract;
 mod economics;
 mod fraction;
 mod lot;
 mod profile;
+mod storage;
 mod utils;
 
 use std::collections::HashSet;
@@ -13,21 +15,23 @@ use std::ops;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{UnorderedMap, UnorderedSet, Vector};
-use near_sdk::json_types::{U128, U64};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde_json;
 use near_sdk::{
     env, ext_contract, log, near_bindgen, AccountId, Balance, Duration, PanicOnDefault, Promise,
-    PromiseResult, PublicKey, Timestamp,
+    PromiseOrValue, PromiseResult, PublicKey, StorageUsage, Timestamp,
 };
 
 pub use crate::api_lot::*;
 pub use crate::api_profile::*;
+pub use crate::api_storage::*;
 pub use crate::contract::*;
 pub use crate::economics::*;
 pub use crate::fraction::*;
 pub use crate::lot::*;
 pub use crate::profile::*;
+pub use crate::storage::*;
 pub use crate::utils::*;
 
 #[allow(
@@ -51,17 +55,41 @@ pub const PREFIX_LOTS: &str = "a";
 pub const PREFIX_LOTS_BIDS: &str = "y";
 pub const PREFIX_PROFILE_LOTS_BIDDING: &str = "b";
 pub const PREFIX_PROFILE_LOTS_OFFERING: &str = "f";
+pub const PREFIX_LOT_BUNDLE_MEMBERS: &str = "m";
+pub const PREFIX_PROFILE_LOTS_WATCHING: &str = "w";
+pub const PREFIX_LOTS_WATCHERS: &str = "v";
+pub const PREFIX_STORAGE_ACCOUNTS: &str = "s";
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockInfoView {
+    pub owner_id: AccountId,
+    pub original_owner_id: AccountId,
+    pub lock_timestamp: WrappedTimestamp,
+    pub is_unlocked: bool,
+}
 
 #[ext_contract]
 pub trait ExtLockContract {
-    fn unlock(&mut self, public_key: PublicKey);
+    fn unlock(&mut self, public_key: PublicKey, new_code: Option<Base64VecU8>);
+    fn sweep_to(
+        &mut self,
+        beneficiary_id: AccountId,
+        keep_amount: WrappedBalance,
+    ) -> WrappedBalance;
+    fn get_lock_info(&self) -> LockInfoView;
     fn get_owner(&self) -> AccountId;
 }
 
 #[ext_contract]
 pub trait ExtSelfContract {
-    fn lot_after_claim_clean_up(&mut self, lot_id: LotId);
+    fn lot_after_claim_sweep(&mut self, lot_id: LotId, beneficiary_id: ProfileId);
+    fn lot_after_claim_clean_up(&mut self, lot_id: LotId, max_items: u64);
+    fn lot_after_bundle_join(&mut self, lot_id: LotId, account_id: LotId);
     fn lot_after_remove_unsafe_remove(&mut self, lot_id: LotId);
+    fn lot_after_remove_unsafe_get_owner(&mut self, lot_id: LotId);
+    fn lot_after_reconcile(&mut self, lot_id: LotId);
+    fn lot_after_reconcile_get_owner(&mut self, lot_id: LotId, is_owned: bool);
     fn profile_after_rewards_claim(&mut self, profile_id: ProfileId, rewards: Balance);
 }
 
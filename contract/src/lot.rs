@@ -8,16 +8,64 @@ pub const ERR_LOT_RESERVE_PRICE_TOO_SMALL: &str = "expected greater reserve pric
 pub const ERR_LOT_BID_WRONG_STATUS: &str = "bid: expected status active";
 pub const ERR_LOT_BID_BID_TOO_SMALL: &str = "bid: expected bigger bid";
 pub const ERR_LOT_BID_WRONG_BIDDER: &str = "bid: seller and lot cannot bid";
+pub const ERR_LOT_BID_WRONG_BENEFICIARY: &str = "bid: seller and lot cannot be beneficiary";
+pub const ERR_LOT_BID_RAFFLE: &str = "bid: raffle lot, buy a ticket instead";
+pub const ERR_LOT_TICKET_NOT_RAFFLE: &str = "ticket: expected raffle lot";
+pub const ERR_LOT_TICKET_WRONG_STATUS: &str = "ticket: expected status active";
+pub const ERR_LOT_TICKET_WRONG_PRICE: &str = "ticket: expected ticket price";
+pub const ERR_LOT_TICKET_WRONG_BUYER: &str = "ticket: seller and lot cannot buy";
+pub const ERR_LOT_TICKET_ALREADY_BOUGHT: &str = "ticket: already holds a ticket";
+pub const ERR_LOT_TICKET_SOLD_OUT: &str = "ticket: sold out";
+pub const ERR_LOT_DRAW_WRONG_STATUS: &str = "draw: expected status draw pending";
+pub const ERR_LOT_CLAIM_ALREADY_PENDING: &str = "claim: already pending";
+pub const ERR_LOT_CLAIM_CLEANING_UP: &str = "claim: lot is cleaning up";
 pub const ERR_LOT_CLAIM_BY_BIDDER_WRONG_STATUS: &str =
     "claim by bidder: expected status sale success";
 pub const ERR_LOT_CLAIM_BY_BIDDER_WRONG_CLAIMER: &str = "claim by bidder: wrong claimer";
-pub const ERR_LOT_CLAIM_BY_SELLER_WRONG_STATUS: &str = "claim by seller: expected status withdrawn";
+pub const ERR_LOT_CLAIM_BY_SELLER_WRONG_STATUS: &str =
+    "claim by seller: expected status withdrawn or cancelled";
 pub const ERR_LOT_CLAIM_BY_SELLER_WRONG_CLAIMER: &str = "claim by seller: wrong claimer";
+pub const ERR_LOT_FINALIZE_WRONG_STATUS: &str = "finalize: expected status sale success";
+pub const ERR_LOT_FINALIZE_NO_PUBLIC_KEY: &str = "finalize: winning bid has no public key";
+pub const ERR_LOT_FINALIZE_CLAIM_TRANSFERRED: &str = "finalize: claim transferred";
+pub const ERR_LOT_TRANSFER_CLAIM_WRONG_STATUS: &str =
+    "transfer claim: expected status sale success";
+pub const ERR_LOT_TRANSFER_CLAIM_WRONG_CALLER: &str = "transfer claim: wrong caller";
+pub const ERR_LOT_TRANSFER_CLAIM_SAME_OWNER: &str = "transfer claim: already claimer";
+pub const ERR_LOT_TRANSFER_CLAIM_WRONG_OWNER: &str = "transfer claim: seller and lot cannot claim";
+pub const ERR_LOT_BID_RETRACT_WRONG_STATUS: &str = "bid retract: expected status on sale";
+pub const ERR_LOT_BID_RETRACT_WRONG_BIDDER: &str = "bid retract: expected last bidder";
+pub const ERR_LOT_BID_RETRACT_TOO_LATE: &str = "bid retract: retraction window expired";
+pub const ERR_LOT_BID_RETRACT_RAFFLE: &str = "bid retract: raffle tickets cannot be retracted";
+pub const ERR_LOT_CANCEL_WRONG_STATUS: &str = "cancel: expected status on sale";
+pub const ERR_LOT_CANCEL_WRONG_CALLER: &str = "cancel: wrong caller";
+pub const ERR_LOT_CANCEL_NO_BIDS: &str = "cancel: expected bids, withdraw instead";
+pub const ERR_LOT_CANCEL_RAFFLE: &str = "cancel: raffle cannot be cancelled";
+pub const ERR_LOT_EXTEND_WRONG_STATUS: &str = "extend: expected status on sale";
+pub const ERR_LOT_EXTEND_WRONG_CALLER: &str = "extend: wrong caller";
+pub const ERR_LOT_EXTEND_BIDS_EXIST: &str = "extend: bids exist";
+pub const ERR_LOT_EXTEND_NOT_LATER: &str = "extend: expected later finish_timestamp";
+pub const ERR_LOT_EXTEND_TOO_LONG: &str = "extend: expected shorter duration";
+pub const ERR_LOT_BUNDLE_JOIN_WRONG_STATUS: &str = "bundle join: expected status on sale";
+pub const ERR_LOT_BUNDLE_JOIN_BIDS_EXIST: &str = "bundle join: bids exist";
+pub const ERR_LOT_BUNDLE_JOIN_WRONG_ACCOUNT: &str = "bundle join: seller and lot cannot join";
+pub const ERR_LOT_BUNDLE_JOIN_ALREADY_JOINED: &str = "bundle join: already joined";
+pub const ERR_LOT_BUNDLE_JOIN_TOO_MANY: &str = "bundle join: too many accounts";
+pub const ERR_LOT_TRANSFER_CLAIM_PARTIALLY_UNLOCKED: &str =
+    "transfer claim: bundle partially unlocked";
 pub const ERR_LOT_WITHDRAW_HAS_BID: &str = "withdraw: expected no bids";
 pub const ERR_LOT_WITHDRAW_WRONG_STATUS: &str = "withdraw: already withdrawn";
 pub const ERR_LOT_WITHDRAW_WRONG_WITHDRAWER: &str = "withdraw: wrong withdrawer";
+pub const ERR_LOT_WITHDRAW_CLEANING_UP: &str = "withdraw: lot is cleaning up";
 pub const ERR_LOT_REOFFER_WRONG_CALLER: &str = "reoffer: wrong caller";
 pub const ERR_LOT_REOFFER_BIDS_EXIST: &str = "reoffer: bids exist";
+pub const ERR_LOT_RELIST_BIDS_LEFT: &str = "UNREACHABLE: relist with bids left";
+pub const ERR_LOT_REOFFER_CLAIM_PENDING: &str = "reoffer: claim pending";
+pub const ERR_LOT_REOFFER_CLEANING_UP: &str = "reoffer: lot is cleaning up";
+pub const ERR_LOT_REOFFER_CANCELLED: &str = "reoffer: lot is cancelled";
+pub const ERR_LOT_REOFFER_DRAW_PENDING: &str = "reoffer: draw pending";
+pub const ERR_LOT_REOFFER_PARTIALLY_UNLOCKED: &str = "reoffer: bundle partially unlocked";
+pub const ERR_LOT_REOFFER_RAFFLE: &str = "reoffer: raffle cannot be reoffered by the seller";
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum LotStatus {
@@ -25,6 +73,10 @@ pub enum LotStatus {
     Withdrawn,
     SaleSuccess,
     SaleFailure,
+    ClaimPending,
+    CleaningUp,
+    Cancelled,
+    DrawPending,
 }
 
 impl fmt::Display for LotStatus {
@@ -38,6 +90,60 @@ pub struct Bid {
     pub bidder_id: ProfileId,
     pub amount: Balance,
     pub timestamp: Timestamp,
+    // key to install on the lot account, allows anyone to finalize the sale
+    pub public_key: Option<PublicKey>,
+    // kept to reverse the referral reward if the bid is retracted
+    pub referrer_id: Option<ProfileId>,
+    // receives the lot if the bid wins, bidder pays and gets the outbid rewards
+    pub beneficiary_id: Option<ProfileId>,
+    // credited when the bid was placed, reversed as is if the bid is retracted
+    pub rewards: BidRewards,
+    // charged to the bidder storage balance, released when the bid is removed
+    pub storage_usage: StorageUsage,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct BidRewards {
+    pub to_prev_bidder: Balance,
+    pub to_seller: Balance,
+    pub to_referrer: Balance,
+}
+
+impl Bid {
+    pub fn claimer_id(&self) -> &ProfileId {
+        self.beneficiary_id.as_ref().unwrap_or(&self.bidder_id)
+    }
+
+    // bidder and beneficiary, both have the lot in lots_bidding
+    pub fn account_ids(&self) -> Vec<ProfileId> {
+        std::iter::once(self.bidder_id.clone())
+            .chain(self.beneficiary_id.clone())
+            .collect()
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct Raffle {
+    pub ticket_price: Balance,
+    pub winner_id: Option<ProfileId>,
+}
+
+// relist by the winner, applied once bids of the previous sale are cleaned up
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct LotRelist {
+    pub seller_id: ProfileId,
+    pub reserve_price: Balance,
+    pub buy_now_price: Balance,
+    pub duration: Duration,
+    pub prev_bidder_commission_share: Option<Fraction>,
+}
+
+// restarts the lot expired without bids, dropping the reserve price by price_drop each round
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy)]
+pub struct RelistPolicy {
+    pub round: u32,
+    pub rounds_left: u32,
+    pub price_drop: Fraction,
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -48,10 +154,45 @@ pub struct Lot {
     pub buy_now_price: Balance,
     pub start_timestamp: Timestamp,
     pub finish_timestamp: Timestamp,
+    // start of the offer, not moved by relist rounds, caps the extend duration
+    pub original_start_timestamp: Timestamp,
     pub is_withdrawn: bool,
+    // cancelled by seller with bids, top bidder compensated, only seller can claim
+    pub is_cancelled: bool,
+    pub sweep_on_claim: bool,
+    pub is_claim_pending: bool,
+    // when the pending claim was started, reconcile doesn't revert it earlier than the grace
+    pub claim_pending_timestamp: Timestamp,
+    pub is_cleaning_up: bool,
+    // set if the winner transferred the right to claim
+    pub claimer_id_override: Option<ProfileId>,
+    // seller proceeds shares, first one gets the rounding dust, empty means all to seller
+    pub payouts: Vec<(ProfileId, Fraction)>,
+    // seller chosen outbid reward share, none means contract default
+    pub prev_bidder_commission_share: Option<Fraction>,
+    // seller chosen bid step, none means contract default
+    pub bid_step: Option<Fraction>,
+    // absolute floor of the bid increment, applied if greater than the fractional step
+    pub bid_step_min_increment: Balance,
+    // applied lazily, see touch
+    pub relist_policy: Option<RelistPolicy>,
+    // fixed price raffle instead of auction, tickets are kept as bids
+    pub raffle: Option<Raffle>,
+    // other locked accounts sold together with the lot account
+    pub bundle_ids: Vec<LotId>,
+    // accounts already unlocked by the claim, failed unlocks are retried by claiming again
+    pub unlocked_ids: Vec<LotId>,
+    // set while the lot is cleaning up before the relist by the winner
+    pub relist_pending: Option<LotRelist>,
+    // charged to the storage balance of the storage payer for the lot and its associations
+    // other than bids, released to the payer when the lot is removed
+    pub storage_usage: StorageUsage,
 
     bids: Vector<Bid>,
     last_bid: Option<Bid>,
+    // profiles having the lot in lots_watching with the storage charged to them,
+    // removed on clean up
+    watchers: UnorderedMap<ProfileId, StorageUsage>,
 }
 
 impl Lot {
@@ -63,6 +204,63 @@ impl Lot {
         start_timestamp: Timestamp,
         finish_timestamp: Timestamp,
     ) -> Lot {
+        Self::validate_new(
+            &lot_id,
+            &seller_id,
+            reserve_price,
+            buy_now_price,
+            start_timestamp,
+            finish_timestamp,
+        );
+
+        // TODO: do we still need to hash the key
+        let mut prefix: Vec<u8> = Vec::with_capacity(33);
+        prefix.extend(PREFIX_LOTS_BIDS.as_bytes());
+        prefix.extend(env::sha256(lot_id.as_bytes()));
+
+        let mut prefix_watchers: Vec<u8> = Vec::with_capacity(33);
+        prefix_watchers.extend(PREFIX_LOTS_WATCHERS.as_bytes());
+        prefix_watchers.extend(env::sha256(lot_id.as_bytes()));
+
+        Lot {
+            lot_id,
+            seller_id,
+            reserve_price,
+            buy_now_price,
+            start_timestamp,
+            finish_timestamp,
+            original_start_timestamp: start_timestamp,
+            is_withdrawn: false,
+            is_cancelled: false,
+            sweep_on_claim: false,
+            is_claim_pending: false,
+            claim_pending_timestamp: 0,
+            is_cleaning_up: false,
+            claimer_id_override: None,
+            payouts: vec![],
+            prev_bidder_commission_share: None,
+            bid_step: None,
+            bid_step_min_increment: 0,
+            relist_policy: None,
+            raffle: None,
+            bundle_ids: vec![],
+            unlocked_ids: vec![],
+            relist_pending: None,
+            storage_usage: 0,
+            bids: Vector::new(prefix),
+            last_bid: None,
+            watchers: UnorderedMap::new(prefix_watchers),
+        }
+    }
+
+    pub fn validate_new(
+        lot_id: &LotId,
+        seller_id: &ProfileId,
+        reserve_price: Balance,
+        buy_now_price: Balance,
+        start_timestamp: Timestamp,
+        finish_timestamp: Timestamp,
+    ) {
         assert_ne!(lot_id, seller_id, "{}", ERR_LOT_SELLS_SELF);
         assert!(
             reserve_price <= buy_now_price,
@@ -84,41 +282,170 @@ impl Lot {
             "{}",
             ERR_LOT_DURATION_TOO_LONG,
         );
+    }
 
-        // TODO: do we still need to hash the key
-        let mut prefix: Vec<u8> = Vec::with_capacity(33);
-        prefix.extend(PREFIX_LOTS_BIDS.as_bytes());
-        prefix.extend(env::sha256(lot_id.as_bytes()));
-
-        Lot {
-            lot_id,
-            seller_id,
+    // starts a new sale of the lot in place, watchers and bundled accounts are kept,
+    // seller parameters are reset as for a new lot
+    pub fn relist(
+        &mut self,
+        seller_id: ProfileId,
+        reserve_price: Balance,
+        buy_now_price: Balance,
+        start_timestamp: Timestamp,
+        finish_timestamp: Timestamp,
+    ) {
+        Self::validate_new(
+            &self.lot_id,
+            &seller_id,
             reserve_price,
             buy_now_price,
             start_timestamp,
             finish_timestamp,
-            is_withdrawn: false,
-            bids: Vector::new(prefix),
-            last_bid: None,
-        }
+        );
+        assert!(self.bids.is_empty(), "{}", ERR_LOT_RELIST_BIDS_LEFT);
+
+        self.seller_id = seller_id;
+        self.reserve_price = reserve_price;
+        self.buy_now_price = buy_now_price;
+        self.start_timestamp = start_timestamp;
+        self.finish_timestamp = finish_timestamp;
+        self.original_start_timestamp = start_timestamp;
+        self.is_withdrawn = false;
+        self.is_cancelled = false;
+        self.is_claim_pending = false;
+        self.claim_pending_timestamp = 0;
+        self.is_cleaning_up = false;
+        self.claimer_id_override = None;
+        self.payouts = vec![];
+        self.prev_bidder_commission_share = None;
+        self.bid_step = None;
+        self.bid_step_min_increment = 0;
+        self.relist_policy = None;
+        self.raffle = None;
+        self.unlocked_ids = vec![];
+        self.relist_pending = None;
+        self.last_bid = None;
     }
 
     pub fn bids(&self) -> Vec<Bid> {
         self.bids.to_vec()
     }
 
+    pub fn bids_count(&self) -> u64 {
+        self.bids.len()
+    }
+
+    // the seller pays for the lot storage, the winner relisting the lot takes it over
+    pub fn storage_payer_id(&self) -> &ProfileId {
+        self.relist_pending
+            .as_ref()
+            .map(|x| &x.seller_id)
+            .unwrap_or(&self.seller_id)
+    }
+
+    // records the storage charged to the bidder for the bid or ticket placed last
+    pub fn last_bid_storage_charged(&mut self, storage_usage: StorageUsage) {
+        let index = self.bids.len() - 1;
+        let mut bid = self.bids.get(index).unwrap();
+        bid.storage_usage = storage_usage;
+        self.bids.replace(index, &bid);
+        if self.last_bid.is_some() {
+            self.last_bid = Some(bid);
+        }
+    }
+
+    // relist rounds passed since the stored one, not yet applied by touch
+    fn relist_rounds_pending(&self, time_now: Timestamp) -> u32 {
+        let policy = match self.relist_policy {
+            Some(policy) => policy,
+            None => return 0,
+        };
+        if policy.rounds_left == 0
+            || time_now < self.finish_timestamp
+            || self.last_bid.is_some()
+            || self.is_withdrawn
+            || self.is_cancelled
+            || self.is_claim_pending
+            || self.is_cleaning_up
+        {
+            return 0;
+        }
+        let duration = self.finish_timestamp - self.start_timestamp;
+        if duration == 0 {
+            return 0;
+        }
+        let rounds = (time_now - self.finish_timestamp) / duration + 1;
+
+        std::cmp::min(rounds, policy.rounds_left as u64) as u32
+    }
+
+    // start_timestamp, finish_timestamp and reserve_price of the round current at time_now
+    pub fn relist_round_state(&self, time_now: Timestamp) -> (Timestamp, Timestamp, Balance) {
+        let rounds = self.relist_rounds_pending(time_now);
+        if rounds == 0 {
+            return (
+                self.start_timestamp,
+                self.finish_timestamp,
+                self.reserve_price,
+            );
+        }
+
+        let price_drop = self.relist_policy.unwrap().price_drop;
+        let shift = (self.finish_timestamp - self.start_timestamp) * rounds as u64;
+        let reserve_price = (0..rounds).fold(self.reserve_price, |reserve_price, _| {
+            std::cmp::max(
+                reserve_price - price_drop * reserve_price,
+                LOT_OFFER_MIN_RESERVE_PRICE,
+            )
+        });
+
+        (
+            self.start_timestamp + shift,
+            self.finish_timestamp + shift,
+            reserve_price,
+        )
+    }
+
+    pub fn relist_policy_at(&self, time_now: Timestamp) -> Option<RelistPolicy> {
+        let rounds = self.relist_rounds_pending(time_now);
+        self.relist_policy.map(|policy| RelistPolicy {
+            round: policy.round + rounds,
+            rounds_left: policy.rounds_left - rounds,
+            price_drop: policy.price_drop,
+        })
+    }
+
+    // stores the relist round current at time_now, expected before any mutation
+    pub fn touch(&mut self, time_now: Timestamp) {
+        if self.relist_rounds_pending(time_now) == 0 {
+            return;
+        }
+        let (start_timestamp, finish_timestamp, reserve_price) = self.relist_round_state(time_now);
+        self.relist_policy = self.relist_policy_at(time_now);
+        self.start_timestamp = start_timestamp;
+        self.finish_timestamp = finish_timestamp;
+        self.reserve_price = reserve_price;
+    }
+
     pub fn is_active(&self, time_now: Timestamp) -> bool {
-        if time_now >= self.finish_timestamp {
+        let (_, finish_timestamp, _) = self.relist_round_state(time_now);
+        if time_now >= finish_timestamp {
             return false;
         }
         if let Some(last_bid_amount) = self.last_bid_amount() {
-            if last_bid_amount >= self.buy_now_price {
+            if last_bid_amount >= self.buy_now_price && self.raffle.is_none() {
                 return false;
             }
         }
         if self.is_withdrawn {
             return false;
         }
+        if self.is_cancelled {
+            return false;
+        }
+        if self.is_cleaning_up {
+            return false;
+        }
 
         true
     }
@@ -131,41 +458,123 @@ impl Lot {
         self.last_bid().map(|x| x.amount)
     }
 
-    pub fn next_bid_amount(&self, time_now: Timestamp, bid_step: Fraction) -> Option<Balance> {
+    pub fn next_bid_amount(
+        &self,
+        time_now: Timestamp,
+        default_bid_step: Fraction,
+    ) -> Option<Balance> {
         if !self.is_active(time_now) {
             return None;
         }
+        if let Some(raffle) = self.raffle.as_ref() {
+            return Some(raffle.ticket_price);
+        }
         if let Some(last_bid_amount) = self.last_bid_amount() {
-            let mut next_bid_amount = last_bid_amount + bid_step * last_bid_amount;
+            let bid_step = self.bid_step.unwrap_or(default_bid_step);
+            let increment = std::cmp::max(bid_step * last_bid_amount, self.bid_step_min_increment);
+            let mut next_bid_amount = last_bid_amount.saturating_add(increment);
             if next_bid_amount == last_bid_amount {
                 next_bid_amount += 1;
             }
             Some(std::cmp::min(next_bid_amount, self.buy_now_price))
         } else {
-            Some(self.reserve_price)
+            let (_, _, reserve_price) = self.relist_round_state(time_now);
+            Some(reserve_price)
         }
     }
 
     pub fn potential_claimer_id(&self) -> Option<ProfileId> {
-        self.last_bid().map(|x| x.bidder_id)
+        if self.is_cancelled {
+            return None;
+        }
+        if let Some(raffle) = self.raffle.as_ref() {
+            return self
+                .claimer_id_override
+                .clone()
+                .or_else(|| raffle.winner_id.clone());
+        }
+        self.claimer_id_override
+            .clone()
+            .or_else(|| self.last_bid().map(|x| x.claimer_id().clone()))
     }
 
     pub fn status(&self, time_now: Timestamp) -> LotStatus {
-        if self.is_active(time_now) {
+        if self.is_cleaning_up {
+            LotStatus::CleaningUp
+        } else if self.is_claim_pending {
+            LotStatus::ClaimPending
+        } else if self.is_active(time_now) {
             LotStatus::OnSale
+        } else if self.is_cancelled {
+            LotStatus::Cancelled
         } else if self.is_withdrawn {
             LotStatus::Withdrawn
         } else {
             match self.last_bid() {
+                Some(_)
+                    if matches!(
+                        self.raffle,
+                        Some(Raffle {
+                            winner_id: None,
+                            ..
+                        })
+                    ) =>
+                {
+                    LotStatus::DrawPending
+                }
                 Some(_) => LotStatus::SaleSuccess,
                 None => LotStatus::SaleFailure,
             }
         }
     }
 
-    pub fn clean_up(&mut self) {
-        self.bids.clear();
+    // marks lot as cleaning up and removes up to max_items bids, returns removed bids
+    pub fn clean_up_step(&mut self, max_items: u64) -> Vec<Bid> {
+        self.is_cleaning_up = true;
         self.last_bid = None;
+        (0..max_items).map_while(|_| self.bids.pop()).collect()
+    }
+
+    // removes up to max_items watchers, returns removed watchers with their charged storage
+    pub fn clean_up_watchers_step(&mut self, max_items: u64) -> Vec<(ProfileId, StorageUsage)> {
+        self.is_cleaning_up = true;
+        let watchers: Vec<(ProfileId, StorageUsage)> = self
+            .watchers
+            .iter()
+            .take(max_items.try_into().unwrap_or(usize::MAX))
+            .collect();
+        for (watcher_id, _) in watchers.iter() {
+            self.watchers.remove(watcher_id);
+        }
+
+        watchers
+    }
+
+    pub fn is_clean_up_finished(&self) -> bool {
+        self.is_cleaning_up && self.bids.is_empty() && self.watchers.is_empty()
+    }
+
+    // returns false if already watching
+    pub fn watch(&mut self, profile_id: &ProfileId) -> bool {
+        if self.watchers.get(profile_id).is_some() {
+            return false;
+        }
+        self.watchers.insert(profile_id, &0);
+        true
+    }
+
+    // records the storage charged to the watcher, expects the profile is watching
+    pub fn watch_storage_charged(&mut self, profile_id: &ProfileId, storage_usage: StorageUsage) {
+        self.watchers.insert(profile_id, &storage_usage);
+    }
+
+    // returns the storage charged to the watcher, None if not watching
+    pub fn unwatch(&mut self, profile_id: &ProfileId) -> Option<StorageUsage> {
+        self.watchers.remove(profile_id)
+    }
+
+    pub fn watchers_count(&self) -> u64 {
+        self.watchers.len()
     }
 
     fn validate_claim_by_buyer(&self, claimer_id: &ProfileId, time_now: Timestamp) {
@@ -184,7 +593,7 @@ impl Lot {
 
     fn validate_claim_by_seller(&self, claimer_id: &ProfileId) {
         assert!(
-            self.is_withdrawn,
+            self.is_withdrawn || self.is_cancelled,
             "{}",
             ERR_LOT_CLAIM_BY_SELLER_WRONG_STATUS,
         );
@@ -196,6 +605,8 @@ impl Lot {
     }
 
     pub fn validate_claim(&self, claimer_id: &ProfileId, time_now: Timestamp) {
+        assert!(!self.is_cleaning_up, "{}", ERR_LOT_CLAIM_CLEANING_UP);
+        assert!(!self.is_claim_pending, "{}", ERR_LOT_CLAIM_ALREADY_PENDING);
         if claimer_id == &self.seller_id {
             self.validate_claim_by_seller(claimer_id)
         } else {
@@ -203,8 +614,124 @@ impl Lot {
         }
     }
 
+    // blocks other claims and mutations until the unlock result is known
+    pub fn claim(&mut self, claimer_id: &ProfileId, time_now: Timestamp) {
+        self.validate_claim(claimer_id, time_now);
+        self.is_claim_pending = true;
+        self.claim_pending_timestamp = time_now;
+    }
+
+    pub fn claim_revert(&mut self) {
+        self.is_claim_pending = false;
+    }
+
+    // the unlock chain of the pending claim had enough time to finish
+    pub fn is_claim_pending_expired(&self, time_now: Timestamp) -> bool {
+        self.is_claim_pending
+            && time_now >= self.claim_pending_timestamp + LOT_CLAIM_PENDING_GRACE_DURATION
+    }
+
+    // lot account first, then bundled accounts, skipping already unlocked ones
+    pub fn account_ids_locked(&self) -> Vec<LotId> {
+        std::iter::once(&self.lot_id)
+            .chain(self.bundle_ids.iter())
+            .filter(|x| !self.unlocked_ids.contains(x))
+            .cloned()
+            .collect()
+    }
+
+    // records accounts unlocked by the pending claim, returns true if nothing is locked anymore,
+    // otherwise reverts the claim to unlock the rest with another claim
+    pub fn claim_unlocked(&mut self, unlocked_ids: Vec<LotId>) -> bool {
+        self.unlocked_ids.extend(unlocked_ids);
+        if self.account_ids_locked().is_empty() {
+            true
+        } else {
+            self.claim_revert();
+            false
+        }
+    }
+
+    fn validate_finalize(&self, time_now: Timestamp) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::SaleSuccess,
+            "{}",
+            ERR_LOT_FINALIZE_WRONG_STATUS,
+        );
+        assert!(
+            self.claimer_id_override.is_none(),
+            "{}",
+            ERR_LOT_FINALIZE_CLAIM_TRANSFERRED,
+        );
+        assert!(
+            self.last_bid().unwrap().public_key.is_some(),
+            "{}",
+            ERR_LOT_FINALIZE_NO_PUBLIC_KEY,
+        );
+    }
+
+    // claim on behalf of the winner, returns the public key from the winning bid
+    pub fn finalize(&mut self, time_now: Timestamp) -> PublicKey {
+        self.validate_finalize(time_now);
+        self.is_claim_pending = true;
+        self.claim_pending_timestamp = time_now;
+        self.last_bid().unwrap().public_key.unwrap()
+    }
+
+    fn validate_transfer_claim(
+        &self,
+        caller_id: &ProfileId,
+        new_owner_id: &ProfileId,
+        time_now: Timestamp,
+    ) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::SaleSuccess,
+            "{}",
+            ERR_LOT_TRANSFER_CLAIM_WRONG_STATUS,
+        );
+        assert_eq!(
+            self.potential_claimer_id().as_ref(),
+            Some(caller_id),
+            "{}",
+            ERR_LOT_TRANSFER_CLAIM_WRONG_CALLER,
+        );
+        assert_ne!(
+            caller_id, new_owner_id,
+            "{}",
+            ERR_LOT_TRANSFER_CLAIM_SAME_OWNER,
+        );
+        assert_ne!(
+            &self.seller_id, new_owner_id,
+            "{}",
+            ERR_LOT_TRANSFER_CLAIM_WRONG_OWNER,
+        );
+        assert_ne!(
+            &self.lot_id, new_owner_id,
+            "{}",
+            ERR_LOT_TRANSFER_CLAIM_WRONG_OWNER,
+        );
+        assert!(
+            self.unlocked_ids.is_empty(),
+            "{}",
+            ERR_LOT_TRANSFER_CLAIM_PARTIALLY_UNLOCKED,
+        );
+    }
+
+    pub fn transfer_claim(
+        &mut self,
+        caller_id: &ProfileId,
+        new_owner_id: &ProfileId,
+        time_now: Timestamp,
+    ) {
+        self.validate_transfer_claim(caller_id, new_owner_id, time_now);
+        self.claimer_id_override = Some(new_owner_id.clone());
+    }
+
     // add status
     fn validate_withdraw(&self, withdrawer_id: &ProfileId) {
+        assert!(!self.is_cleaning_up, "{}", ERR_LOT_WITHDRAW_CLEANING_UP);
         assert!(!self.is_withdrawn, "{}", ERR_LOT_WITHDRAW_WRONG_STATUS);
         assert!(self.last_bid().is_none(), "{}", ERR_LOT_WITHDRAW_HAS_BID);
         assert_eq!(
@@ -219,13 +746,116 @@ impl Lot {
         self.is_withdrawn = true;
     }
 
-    fn validate_place_bid(&mut self, bid: &Bid, bid_step: Fraction) {
+    fn validate_extend(
+        &self,
+        caller_id: &ProfileId,
+        finish_timestamp: Timestamp,
+        time_now: Timestamp,
+    ) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::OnSale,
+            "{}",
+            ERR_LOT_EXTEND_WRONG_STATUS,
+        );
+        assert_eq!(
+            &self.seller_id, caller_id,
+            "{}",
+            ERR_LOT_EXTEND_WRONG_CALLER
+        );
+        assert!(self.last_bid.is_none(), "{}", ERR_LOT_EXTEND_BIDS_EXIST);
+        assert!(
+            finish_timestamp > self.finish_timestamp,
+            "{}",
+            ERR_LOT_EXTEND_NOT_LATER,
+        );
+        assert!(
+            self.original_start_timestamp + LOT_OFFER_MAX_DURATION >= finish_timestamp,
+            "{}",
+            ERR_LOT_EXTEND_TOO_LONG,
+        );
+    }
+
+    // moves the deadline of the lot without bids, duration limit counts from the original start
+    pub fn extend(
+        &mut self,
+        caller_id: &ProfileId,
+        finish_timestamp: Timestamp,
+        time_now: Timestamp,
+    ) {
+        self.validate_extend(caller_id, finish_timestamp, time_now);
+        self.finish_timestamp = finish_timestamp;
+    }
+
+    fn validate_cancel(&self, caller_id: &ProfileId, time_now: Timestamp) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::OnSale,
+            "{}",
+            ERR_LOT_CANCEL_WRONG_STATUS,
+        );
+        assert_eq!(
+            &self.seller_id, caller_id,
+            "{}",
+            ERR_LOT_CANCEL_WRONG_CALLER
+        );
+        assert!(self.last_bid.is_some(), "{}", ERR_LOT_CANCEL_NO_BIDS);
+        assert!(self.raffle.is_none(), "{}", ERR_LOT_CANCEL_RAFFLE);
+    }
+
+    // withdraw for lot with bids, returns the top bid to compensate
+    pub fn cancel(&mut self, caller_id: &ProfileId, time_now: Timestamp) -> Bid {
+        self.validate_cancel(caller_id, time_now);
+        self.is_cancelled = true;
+        self.last_bid().unwrap()
+    }
+
+    // the account joins by itself, see lot_bundle_join
+    pub fn validate_bundle_join(&self, account_id: &LotId, time_now: Timestamp) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::OnSale,
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_WRONG_STATUS,
+        );
+        assert!(
+            self.last_bid.is_none(),
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_BIDS_EXIST
+        );
+        assert!(
+            account_id != &self.lot_id && account_id != &self.seller_id,
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_WRONG_ACCOUNT,
+        );
+        assert!(
+            !self.bundle_ids.contains(account_id),
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_ALREADY_JOINED,
+        );
+        assert!(
+            (self.bundle_ids.len() as u64) < LOT_BUNDLE_MAX_LEN,
+            "{}",
+            ERR_LOT_BUNDLE_JOIN_TOO_MANY,
+        );
+    }
+
+    // adds another locked account of the same seller to the lot before the first bid
+    pub fn bundle_join(&mut self, account_id: &LotId, time_now: Timestamp) {
+        self.validate_bundle_join(account_id, time_now);
+        self.bundle_ids.push(account_id.clone());
+    }
+
+    fn validate_place_bid(&mut self, bid: &Bid, default_bid_step: Fraction) {
+        assert!(self.raffle.is_none(), "{}", ERR_LOT_BID_RAFFLE);
         assert!(
             self.is_active(bid.timestamp),
             "{}",
             ERR_LOT_BID_WRONG_STATUS
         );
-        let min_next_bid_amount = self.next_bid_amount(bid.timestamp, bid_step).unwrap();
+        let min_next_bid_amount = self
+            .next_bid_amount(bid.timestamp, default_bid_step)
+            .unwrap();
         assert!(
             bid.amount >= min_next_bid_amount,
             "{}",
@@ -237,55 +867,183 @@ impl Lot {
             ERR_LOT_BID_WRONG_BIDDER
         );
         assert_ne!(self.lot_id, bid.bidder_id, "{}", ERR_LOT_BID_WRONG_BIDDER);
+        if let Some(beneficiary_id) = bid.beneficiary_id.as_ref() {
+            assert!(
+                beneficiary_id != &self.seller_id && beneficiary_id != &self.lot_id,
+                "{}",
+                ERR_LOT_BID_WRONG_BENEFICIARY,
+            );
+        }
     }
 
-    pub fn place_bid(&mut self, bid: &Bid, bid_step: Fraction) {
-        self.validate_place_bid(bid, bid_step);
+    pub fn place_bid(&mut self, bid: &Bid, default_bid_step: Fraction) {
+        self.validate_place_bid(bid, default_bid_step);
         self.bids.push(bid);
         self.last_bid = Some(bid.clone());
     }
 
-    pub fn validate_reoffer(&self, caller_id: &ProfileId) {
+    pub fn has_ticket(&self, account_id: &ProfileId) -> bool {
+        self.bids.iter().any(|x| &x.bidder_id == account_id)
+    }
+
+    fn validate_buy_ticket(&self, ticket: &Bid) {
+        let raffle = self.raffle.as_ref().expect(ERR_LOT_TICKET_NOT_RAFFLE);
+        assert!(
+            self.is_active(ticket.timestamp),
+            "{}",
+            ERR_LOT_TICKET_WRONG_STATUS,
+        );
         assert_eq!(
-            caller_id, &self.seller_id,
+            ticket.amount, raffle.ticket_price,
+            "{}",
+            ERR_LOT_TICKET_WRONG_PRICE,
+        );
+        assert!(
+            ticket.bidder_id != self.seller_id && ticket.bidder_id != self.lot_id,
+            "{}",
+            ERR_LOT_TICKET_WRONG_BUYER,
+        );
+        assert!(
+            !self.has_ticket(&ticket.bidder_id),
+            "{}",
+            ERR_LOT_TICKET_ALREADY_BOUGHT,
+        );
+        assert!(
+            self.bids.len() < LOT_RAFFLE_MAX_TICKETS,
             "{}",
-            ERR_LOT_REOFFER_WRONG_CALLER,
+            ERR_LOT_TICKET_SOLD_OUT,
         );
-        assert!(self.last_bid.is_none(), "{}", ERR_LOT_REOFFER_BIDS_EXIST,);
     }
-}
-
-#[cfg(test)]
-pub mod tests {
-    use crate::tests::*;
-
-    pub fn create_lot_x_sells_y(seller_id: &ProfileId, lot_id: &LotId) -> Lot {
-        let reserve_price = to_yocto("2");
-        let buy_now_price = to_yocto("10");
 
-        let start_timestamp = to_ts(10);
-        let finish_timestamp = to_ts(17);
+    // one ticket per participant, tickets are refunded on draw except the winning one
+    pub fn buy_ticket(&mut self, ticket: &Bid) {
+        self.validate_buy_ticket(ticket);
+        self.bids.push(ticket);
+        self.last_bid = Some(ticket.clone());
+    }
 
-        Lot::new(
-            lot_id.clone(),
-            seller_id.clone(),
-            reserve_price,
-            buy_now_price,
-            start_timestamp,
-            finish_timestamp,
-        )
+    fn validate_draw(&self, time_now: Timestamp) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::DrawPending,
+            "{}",
+            ERR_LOT_DRAW_WRONG_STATUS,
+        );
     }
 
-    pub fn create_lot_alice() -> (Lot, Timestamp) {
-        let lot = create_lot_x_sells_y(&"bob".parse().unwrap(), &"alice".parse().unwrap());
-        let time_now = to_ts(16);
+    // picks the winning ticket by the first 8 bytes of the seed,
+    // returns the winning ticket and the tickets to refund
+    pub fn draw(&mut self, seed: &[u8], time_now: Timestamp) -> (Bid, Vec<Bid>) {
+        self.validate_draw(time_now);
+        let mut tickets = self.bids();
+        let index = u64::from_le_bytes(seed[..8].try_into().unwrap()) % tickets.len() as u64;
+        let winning_ticket = tickets.remove(index as usize);
+        self.raffle.as_mut().unwrap().winner_id = Some(winning_ticket.bidder_id.clone());
 
-        (lot, time_now)
+        (winning_ticket, tickets)
     }
 
-    pub fn create_lot_alice_withdrawn() -> (Lot, Timestamp) {
-        let (mut lot, time_now) = create_lot_alice();
-        lot.is_withdrawn = true;
+    pub fn validate_bid_retract(&self, bidder_id: &ProfileId, time_now: Timestamp) {
+        assert_eq!(
+            self.status(time_now),
+            LotStatus::OnSale,
+            "{}",
+            ERR_LOT_BID_RETRACT_WRONG_STATUS,
+        );
+        assert!(self.raffle.is_none(), "{}", ERR_LOT_BID_RETRACT_RAFFLE);
+        let last_bid = self.last_bid().expect(ERR_LOT_BID_RETRACT_WRONG_BIDDER);
+        assert_eq!(
+            &last_bid.bidder_id, bidder_id,
+            "{}",
+            ERR_LOT_BID_RETRACT_WRONG_BIDDER,
+        );
+        assert!(
+            time_now < last_bid.timestamp + LOT_BID_RETRACT_DURATION,
+            "{}",
+            ERR_LOT_BID_RETRACT_TOO_LATE,
+        );
+    }
+
+    // removes the last bid and restores the previous one, returns the removed bid
+    pub fn bid_retract(&mut self, bidder_id: &ProfileId, time_now: Timestamp) -> Bid {
+        self.validate_bid_retract(bidder_id, time_now);
+        let bid = self.bids.pop().unwrap();
+        self.last_bid = if self.bids.is_empty() {
+            None
+        } else {
+            self.bids.get(self.bids.len() - 1)
+        };
+
+        bid
+    }
+
+    // seller can reoffer the lot without bids, winner can relist the won lot as the new seller
+    pub fn validate_reoffer(&self, caller_id: &ProfileId, time_now: Timestamp) {
+        assert!(
+            self.unlocked_ids.is_empty(),
+            "{}",
+            ERR_LOT_REOFFER_PARTIALLY_UNLOCKED,
+        );
+        match self.status(time_now) {
+            LotStatus::OnSale | LotStatus::Withdrawn | LotStatus::SaleFailure => {
+                assert_eq!(
+                    caller_id, &self.seller_id,
+                    "{}",
+                    ERR_LOT_REOFFER_WRONG_CALLER,
+                );
+                assert!(self.last_bid.is_none(), "{}", ERR_LOT_REOFFER_BIDS_EXIST);
+                // reserve and buy now prices of a raffle are the ticket price, relisting it as
+                // an auction would silently drop the raffle
+                assert!(self.raffle.is_none(), "{}", ERR_LOT_REOFFER_RAFFLE);
+            }
+            LotStatus::SaleSuccess => {
+                assert_ne!(caller_id, &self.seller_id, "{}", ERR_LOT_REOFFER_BIDS_EXIST,);
+                assert_eq!(
+                    self.potential_claimer_id().as_ref(),
+                    Some(caller_id),
+                    "{}",
+                    ERR_LOT_REOFFER_WRONG_CALLER,
+                );
+            }
+            LotStatus::ClaimPending => panic!("{}", ERR_LOT_REOFFER_CLAIM_PENDING),
+            LotStatus::CleaningUp => panic!("{}", ERR_LOT_REOFFER_CLEANING_UP),
+            LotStatus::Cancelled => panic!("{}", ERR_LOT_REOFFER_CANCELLED),
+            LotStatus::DrawPending => panic!("{}", ERR_LOT_REOFFER_DRAW_PENDING),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::tests::*;
+
+    pub fn create_lot_x_sells_y(seller_id: &ProfileId, lot_id: &LotId) -> Lot {
+        let reserve_price = to_yocto("2");
+        let buy_now_price = to_yocto("10");
+
+        let start_timestamp = to_ts(10);
+        let finish_timestamp = to_ts(17);
+
+        Lot::new(
+            lot_id.clone(),
+            seller_id.clone(),
+            reserve_price,
+            buy_now_price,
+            start_timestamp,
+            finish_timestamp,
+        )
+    }
+
+    pub fn create_lot_alice() -> (Lot, Timestamp) {
+        let lot = create_lot_x_sells_y(&"bob".parse().unwrap(), &"alice".parse().unwrap());
+        let time_now = to_ts(16);
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_withdrawn() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.is_withdrawn = true;
 
         (lot, time_now)
     }
@@ -304,6 +1062,11 @@ pub mod tests {
                 bidder_id: "carol".parse().unwrap(),
                 amount: to_yocto("3"),
                 timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
             },
             Fraction::new(0, 1),
         );
@@ -312,6 +1075,11 @@ pub mod tests {
                 bidder_id: "dan".parse().unwrap(),
                 amount: to_yocto("6"),
                 timestamp: to_ts(12),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
             },
             Fraction::new(0, 1),
         );
@@ -326,6 +1094,120 @@ pub mod tests {
         (lot, time_now)
     }
 
+    pub fn create_lot_alice_with_bids_claim_pending() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.is_claim_pending = true;
+        lot.claim_pending_timestamp = time_now;
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_with_relist_policy() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.relist_policy = Some(RelistPolicy {
+            round: 0,
+            rounds_left: 2,
+            price_drop: Fraction::new(1, 2),
+        });
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_bundle() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.bundle_join(&"alice-app".parse().unwrap(), time_now);
+        lot.bundle_join(&"alice-dao".parse().unwrap(), time_now);
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_bundle_with_bids_claim_pending() -> (Lot, Timestamp) {
+        let (mut lot, _) = create_lot_alice_bundle();
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("3"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        let time_now = to_ts(18);
+        lot.claim(&"carol".parse().unwrap(), time_now);
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_raffle() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.raffle = Some(Raffle {
+            ticket_price: to_yocto("2"),
+            winner_id: None,
+        });
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_raffle_with_tickets() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice_raffle();
+        for (bidder_id, ts) in [("carol", 11), ("dan", 12), ("frank", 13)] {
+            lot.buy_ticket(&Bid {
+                bidder_id: bidder_id.parse().unwrap(),
+                amount: to_yocto("2"),
+                timestamp: to_ts(ts),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            });
+        }
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_with_bids_cancelled() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.cancel(&"bob".parse().unwrap(), time_now);
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_with_bids_cleaning_up() -> (Lot, Timestamp) {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.clean_up_step(1);
+
+        (lot, time_now)
+    }
+
+    pub fn create_lot_alice_with_bids_public_key_sale_success() -> (Lot, Timestamp) {
+        let (mut lot, _) = create_lot_alice_with_bids();
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("7"),
+                timestamp: to_ts(13),
+                public_key: Some(
+                    "ed25519:KEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYKEYK"
+                        .parse()
+                        .unwrap(),
+                ),
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        let time_now = to_ts(18);
+
+        (lot, time_now)
+    }
+
     pub fn create_lot_alice_buy_now_bid() -> (Lot, Timestamp) {
         let (mut lot, time_now) = create_lot_alice_with_bids();
         lot.place_bid(
@@ -333,6 +1215,11 @@ pub mod tests {
                 bidder_id: "carol".parse().unwrap(),
                 amount: to_yocto("10"),
                 timestamp: to_ts(13),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
             },
             Fraction::new(0, 1),
         );
@@ -350,6 +1237,14 @@ pub mod tests {
         assert_eq!(lot.start_timestamp, to_ts(10), "wrong start_timestamp");
         assert_eq!(lot.finish_timestamp, to_ts(17), "wrong finish_timestamp");
         assert_eq!(lot.is_withdrawn, false, "expected withdrawn false");
+        assert_eq!(lot.sweep_on_claim, false, "expected sweep on claim false");
+        assert_eq!(lot.is_claim_pending, false, "expected claim pending false");
+        assert_eq!(lot.is_cleaning_up, false, "expected cleaning up false");
+        assert!(
+            lot.claimer_id_override.is_none(),
+            "expected no claimer override"
+        );
+        assert!(lot.payouts.is_empty(), "expected empty payouts");
         assert!(lot.bids.is_empty(), "expected bids list is empty");
         assert!(lot.last_bid.is_none(), "expected last bid is none");
     }
@@ -518,6 +1413,59 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_lot_next_bid_amount_lot_bid_step() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.bid_step = Some(Fraction::new(1, 2));
+        assert_eq!(
+            lot.next_bid_amount(time_now, Fraction::new(0, 1)),
+            Some(to_yocto("9")),
+            "expected lot bid step over default",
+        );
+
+        lot.bid_step_min_increment = to_yocto("3.5");
+        assert_eq!(
+            lot.next_bid_amount(time_now, Fraction::new(0, 1)),
+            Some(to_yocto("9.5")),
+            "expected min increment if greater than fractional step",
+        );
+
+        lot.bid_step_min_increment = to_yocto("5");
+        assert_eq!(
+            lot.next_bid_amount(time_now, Fraction::new(0, 1)),
+            Some(to_yocto("10")),
+            "expected buy now price cap",
+        );
+
+        lot.bid_step = None;
+        lot.bid_step_min_increment = Balance::MAX;
+        assert_eq!(
+            lot.next_bid_amount(time_now, Fraction::new(0, 1)),
+            Some(to_yocto("10")),
+            "expected no overflow",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bid: expected bigger bid")]
+    fn test_lot_place_bid_fail_below_min_increment() {
+        let (mut lot, _) = create_lot_alice_with_bids();
+        lot.bid_step_min_increment = to_yocto("1");
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("6.9"),
+                timestamp: to_ts(13),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(1, 100),
+        );
+    }
+
     #[test]
     fn test_lot_potential_claimer_id() {
         let (lot, _) = create_lot_alice();
@@ -527,6 +1475,45 @@ pub mod tests {
         assert_eq!(lot.potential_claimer_id(), Some("dan".parse().unwrap()));
     }
 
+    #[test]
+    fn test_lot_potential_claimer_id_beneficiary() {
+        let (mut lot, _) = create_lot_alice_with_bids();
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("8"),
+                timestamp: to_ts(13),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: Some("eve".parse().unwrap()),
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        assert_eq!(lot.potential_claimer_id(), Some("eve".parse().unwrap()));
+        assert_eq!(lot.last_bid().unwrap().bidder_id, "carol".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "bid: seller and lot cannot be beneficiary")]
+    fn test_lot_place_bid_fail_beneficiary_seller() {
+        let (mut lot, _) = create_lot_alice();
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("3"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: Some("bob".parse().unwrap()),
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+    }
+
     #[test]
     fn test_lot_status() {
         let (lot, time_now) = create_lot_alice();
@@ -545,14 +1532,51 @@ pub mod tests {
 
         let (lot, time_now) = create_lot_alice_withdrawn();
         assert_eq!(lot.status(time_now), LotStatus::Withdrawn);
+
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
+        assert_eq!(lot.status(time_now), LotStatus::ClaimPending);
+
+        let (lot, time_now) = create_lot_alice_with_bids_cleaning_up();
+        assert_eq!(lot.status(time_now), LotStatus::CleaningUp);
     }
 
     #[test]
-    fn test_lot_clean_up() {
+    fn test_lot_clean_up_step() {
         let (mut lot, _) = create_lot_alice_with_bids();
-        lot.clean_up();
+        let removed = lot.clean_up_step(1);
+        assert_eq!(removed.len(), 1, "expected one bid removed");
+        assert_eq!(
+            removed[0].bidder_id,
+            "dan".parse().unwrap(),
+            "wrong bid removed"
+        );
+        assert_eq!(lot.is_cleaning_up, true, "expected cleaning up");
+        assert!(
+            lot.last_bid.is_none(),
+            "expected last bid none after clean up"
+        );
+        assert!(
+            !lot.is_clean_up_finished(),
+            "expected clean up not finished"
+        );
+
+        let removed = lot.clean_up_step(5);
+        assert_eq!(removed.len(), 1, "expected one bid removed");
+        assert_eq!(
+            removed[0].bidder_id,
+            "carol".parse().unwrap(),
+            "wrong bid removed"
+        );
         assert!(lot.bids.is_empty(), "expected bids empty after clean up");
-        assert!(lot.last_bid.is_none(), "expected bids empty after clean up");
+        assert!(lot.is_clean_up_finished(), "expected clean up finished");
+    }
+
+    #[test]
+    #[should_panic(expected = "claim: lot is cleaning up")]
+    fn test_lot_validate_claim_fail_cleaning_up() {
+        let (lot, time_now) = create_lot_alice_with_bids_cleaning_up();
+        let bidder_id: ProfileId = "dan".parse().unwrap();
+        lot.validate_claim(&bidder_id, time_now);
     }
 
     #[test]
@@ -609,6 +1633,118 @@ pub mod tests {
         lot.validate_claim(&bidder_id, time_now);
     }
 
+    #[test]
+    #[should_panic(expected = "claim: already pending")]
+    fn test_lot_validate_claim_fail_claim_pending() {
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
+        let bidder_id: ProfileId = "dan".parse().unwrap();
+        lot.validate_claim(&bidder_id, time_now);
+    }
+
+    #[test]
+    fn test_lot_claim_and_revert() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        let bidder_id: ProfileId = "dan".parse().unwrap();
+
+        lot.claim(&bidder_id, time_now);
+        assert_eq!(lot.is_claim_pending, true, "expected claim pending");
+        assert_eq!(lot.status(time_now), LotStatus::ClaimPending);
+        assert!(!lot.is_claim_pending_expired(time_now));
+        assert!(lot.is_claim_pending_expired(time_now + LOT_CLAIM_PENDING_GRACE_DURATION));
+
+        lot.claim_revert();
+        assert_eq!(lot.is_claim_pending, false, "expected claim not pending");
+        assert_eq!(lot.status(time_now), LotStatus::SaleSuccess);
+    }
+
+    #[test]
+    fn test_lot_finalize() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_public_key_sale_success();
+        let public_key = lot.finalize(time_now);
+        assert_eq!(
+            Some(public_key),
+            lot.last_bid().unwrap().public_key,
+            "wrong public key"
+        );
+        assert_eq!(lot.status(time_now), LotStatus::ClaimPending);
+    }
+
+    #[test]
+    #[should_panic(expected = "finalize: expected status sale success")]
+    fn test_lot_finalize_fail_active() {
+        let (mut lot, _) = create_lot_alice_with_bids_public_key_sale_success();
+        lot.finalize(to_ts(16));
+    }
+
+    #[test]
+    #[should_panic(expected = "finalize: expected status sale success")]
+    fn test_lot_finalize_fail_claim_pending() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_public_key_sale_success();
+        lot.finalize(time_now);
+        lot.finalize(time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "finalize: winning bid has no public key")]
+    fn test_lot_finalize_fail_no_public_key() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.finalize(time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "finalize: claim transferred")]
+    fn test_lot_finalize_fail_claim_transferred() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_public_key_sale_success();
+        lot.transfer_claim(&"carol".parse().unwrap(), &"eve".parse().unwrap(), time_now);
+        lot.finalize(time_now);
+    }
+
+    #[test]
+    fn test_lot_transfer_claim() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success(); // dan is the last bidder
+        let dan: ProfileId = "dan".parse().unwrap();
+        let eve: ProfileId = "eve".parse().unwrap();
+        let frank: ProfileId = "frank".parse().unwrap();
+
+        lot.transfer_claim(&dan, &eve, time_now);
+        assert_eq!(lot.potential_claimer_id(), Some(eve.clone()));
+        lot.validate_claim(&eve, time_now);
+
+        lot.transfer_claim(&eve, &frank, time_now);
+        assert_eq!(lot.potential_claimer_id(), Some(frank));
+        assert_eq!(lot.status(time_now), LotStatus::SaleSuccess);
+    }
+
+    #[test]
+    #[should_panic(expected = "claim by bidder: wrong claimer")]
+    fn test_lot_transfer_claim_fail_claim_by_winner() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        let dan: ProfileId = "dan".parse().unwrap();
+        lot.transfer_claim(&dan, &"eve".parse().unwrap(), time_now);
+        lot.validate_claim(&dan, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "transfer claim: expected status sale success")]
+    fn test_lot_transfer_claim_fail_active() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.transfer_claim(&"dan".parse().unwrap(), &"eve".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "transfer claim: wrong caller")]
+    fn test_lot_transfer_claim_fail_wrong_caller() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.transfer_claim(&"carol".parse().unwrap(), &"eve".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "transfer claim: seller and lot cannot claim")]
+    fn test_lot_transfer_claim_fail_to_seller() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.transfer_claim(&"dan".parse().unwrap(), &"bob".parse().unwrap(), time_now);
+    }
+
     #[test]
     fn test_lot_withdraw() {
         let (mut lot, _) = create_lot_alice();
@@ -648,6 +1784,11 @@ pub mod tests {
             bidder_id: "dan".parse().unwrap(),
             amount: to_yocto("3"),
             timestamp: time_now,
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
         lot.place_bid(&bid, Fraction::new(0, 1));
         assert_eq!(lot.bids.len(), 1, "{}", "expected bids size 1");
@@ -661,6 +1802,11 @@ pub mod tests {
             bidder_id: "dan".parse().unwrap(),
             amount: to_yocto("3"),
             timestamp: time_now,
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
         lot.place_bid(&bid, Fraction::new(0, 1));
     }
@@ -673,6 +1819,11 @@ pub mod tests {
             bidder_id: "dan".parse().unwrap(),
             amount: to_yocto("1"),
             timestamp: time_now,
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
         lot.place_bid(&bid, Fraction::new(0, 1));
     }
@@ -685,6 +1836,11 @@ pub mod tests {
             bidder_id: "dan".parse().unwrap(),
             amount: to_yocto("3"),
             timestamp: time_now,
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
         lot.place_bid(&bid, Fraction::new(0, 1));
     }
@@ -697,6 +1853,11 @@ pub mod tests {
             bidder_id: "bob".parse().unwrap(),
             amount: to_yocto("3"),
             timestamp: time_now,
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
         lot.place_bid(&bid, Fraction::new(0, 1));
     }
@@ -709,31 +1870,426 @@ pub mod tests {
             bidder_id: "alice".parse().unwrap(),
             amount: to_yocto("3"),
             timestamp: time_now,
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
         };
         lot.place_bid(&bid, Fraction::new(0, 1));
     }
 
     #[test]
-    fn test_lot_validate_reoffer_success() {
-        let caller_id: ProfileId = "bob".parse().unwrap();
+    fn test_lot_relist_round_state() {
+        let (lot, _) = create_lot_alice_with_relist_policy();
 
-        let (lot, _) = create_lot_alice();
-        lot.validate_reoffer(&caller_id);
+        assert_eq!(
+            lot.relist_round_state(to_ts(16)),
+            (to_ts(10), to_ts(17), to_yocto("2")),
+            "expected original round",
+        );
+        assert_eq!(lot.status(to_ts(16)), LotStatus::OnSale);
 
-        let (lot, _) = create_lot_alice_withdrawn();
-        lot.validate_reoffer(&caller_id);
+        assert_eq!(
+            lot.relist_round_state(to_ts(17)),
+            (to_ts(17), to_ts(24), to_yocto("1")),
+            "expected first relist round",
+        );
+        assert_eq!(lot.status(to_ts(17)), LotStatus::OnSale);
+        assert_eq!(
+            lot.next_bid_amount(to_ts(17), Fraction::new(0, 1)),
+            Some(to_yocto("1")),
+            "expected dropped reserve price",
+        );
+        assert_eq!(lot.relist_policy_at(to_ts(17)).unwrap().round, 1);
+
+        assert_eq!(
+            lot.relist_round_state(to_ts(24)),
+            (to_ts(24), to_ts(31), to_yocto("0.5")),
+            "expected second relist round",
+        );
+        let policy = lot.relist_policy_at(to_ts(24)).unwrap();
+        assert_eq!(policy.round, 2, "wrong round");
+        assert_eq!(policy.rounds_left, 0, "expected no rounds left");
 
-        let (lot, _) = create_lot_alice_sale_failure();
-        lot.validate_reoffer(&caller_id);
+        assert_eq!(
+            lot.relist_round_state(to_ts(40)),
+            (to_ts(24), to_ts(31), to_yocto("0.5")),
+            "expected last round after rounds exhausted",
+        );
+        assert_eq!(lot.status(to_ts(40)), LotStatus::SaleFailure);
     }
 
     #[test]
-    #[should_panic(expected = "reoffer: wrong caller")]
-    fn test_lot_validate_reoffer_fail_wrong_seller() {
-        let caller_id: ProfileId = "carol".parse().unwrap();
+    fn test_lot_relist_round_state_min_reserve_price() {
+        let (mut lot, _) = create_lot_alice_with_relist_policy();
+        lot.relist_policy.as_mut().unwrap().price_drop = Fraction::new(1, 1);
 
-        let (lot, _) = create_lot_alice();
-        lot.validate_reoffer(&caller_id);
+        assert_eq!(
+            lot.relist_round_state(to_ts(17)).2,
+            LOT_OFFER_MIN_RESERVE_PRICE,
+            "expected reserve price clamped to min",
+        );
+    }
+
+    #[test]
+    fn test_lot_relist_round_state_no_relist() {
+        let (mut lot, _) = create_lot_alice_with_relist_policy();
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("3"),
+                timestamp: to_ts(11),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        assert_eq!(
+            lot.relist_round_state(to_ts(18)),
+            (to_ts(10), to_ts(17), to_yocto("2")),
+            "expected no relist for lot with bids",
+        );
+        assert_eq!(lot.status(to_ts(18)), LotStatus::SaleSuccess);
+
+        let (mut lot, _) = create_lot_alice_with_relist_policy();
+        lot.is_withdrawn = true;
+        assert_eq!(lot.status(to_ts(18)), LotStatus::Withdrawn);
+    }
+
+    #[test]
+    fn test_lot_touch() {
+        let (mut lot, _) = create_lot_alice_with_relist_policy();
+        lot.touch(to_ts(16));
+        assert_eq!(lot.finish_timestamp, to_ts(17), "expected no changes");
+
+        lot.touch(to_ts(20));
+        assert_eq!(lot.start_timestamp, to_ts(17), "wrong start_timestamp");
+        assert_eq!(lot.finish_timestamp, to_ts(24), "wrong finish_timestamp");
+        assert_eq!(lot.reserve_price, to_yocto("1"), "wrong reserve_price");
+        let policy = lot.relist_policy.unwrap();
+        assert_eq!(policy.round, 1, "wrong round");
+        assert_eq!(policy.rounds_left, 1, "wrong rounds left");
+
+        lot.place_bid(
+            &Bid {
+                bidder_id: "carol".parse().unwrap(),
+                amount: to_yocto("1"),
+                timestamp: to_ts(21),
+                public_key: None,
+                referrer_id: None,
+                beneficiary_id: None,
+                rewards: BidRewards::default(),
+                storage_usage: 0,
+            },
+            Fraction::new(0, 1),
+        );
+        lot.touch(to_ts(30));
+        assert_eq!(lot.finish_timestamp, to_ts(24), "expected no relist");
+        assert_eq!(lot.status(to_ts(30)), LotStatus::SaleSuccess);
+    }
+
+    #[test]
+    fn test_lot_extend() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.extend(&"bob".parse().unwrap(), to_ts(30), time_now);
+        assert_eq!(lot.finish_timestamp, to_ts(30), "wrong finish_timestamp");
+        assert_eq!(lot.start_timestamp, to_ts(10), "expected start unchanged");
+        assert_eq!(lot.status(to_ts(20)), LotStatus::OnSale);
+
+        let max_finish_timestamp = lot.start_timestamp + LOT_OFFER_MAX_DURATION;
+        lot.extend(&"bob".parse().unwrap(), max_finish_timestamp, time_now);
+        assert_eq!(lot.finish_timestamp, max_finish_timestamp);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: wrong caller")]
+    fn test_lot_extend_fail_wrong_caller() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.extend(&"carol".parse().unwrap(), to_ts(30), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: bids exist")]
+    fn test_lot_extend_fail_bids_exist() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.extend(&"bob".parse().unwrap(), to_ts(30), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: expected status on sale")]
+    fn test_lot_extend_fail_sale_failure() {
+        let (mut lot, time_now) = create_lot_alice_sale_failure();
+        lot.extend(&"bob".parse().unwrap(), to_ts(30), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: expected status on sale")]
+    fn test_lot_extend_fail_withdrawn() {
+        let (mut lot, time_now) = create_lot_alice_withdrawn();
+        lot.extend(&"bob".parse().unwrap(), to_ts(30), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: expected later finish_timestamp")]
+    fn test_lot_extend_fail_not_later() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.extend(&"bob".parse().unwrap(), to_ts(17), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: expected shorter duration")]
+    fn test_lot_extend_fail_too_long() {
+        let (mut lot, time_now) = create_lot_alice();
+        let finish_timestamp = lot.start_timestamp + LOT_OFFER_MAX_DURATION + 1;
+        lot.extend(&"bob".parse().unwrap(), finish_timestamp, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "extend: expected shorter duration")]
+    fn test_lot_extend_fail_too_long_after_relist_rounds() {
+        let (mut lot, _) = create_lot_alice_with_relist_policy();
+        let time_now = to_ts(20);
+        lot.touch(time_now);
+        assert_eq!(
+            lot.start_timestamp,
+            to_ts(17),
+            "expected start moved by the round"
+        );
+
+        let finish_timestamp = to_ts(10) + LOT_OFFER_MAX_DURATION + 1;
+        lot.extend(&"bob".parse().unwrap(), finish_timestamp, time_now);
+    }
+
+    #[test]
+    fn test_lot_cancel() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        let bid = lot.cancel(&"bob".parse().unwrap(), time_now);
+        assert_eq!(bid.bidder_id, "dan".parse().unwrap(), "expected top bid");
+        assert_eq!(bid.amount, to_yocto("6"), "wrong top bid amount");
+
+        assert!(lot.is_cancelled, "expected cancelled");
+        assert!(!lot.is_active(time_now), "expected inactive");
+        assert_eq!(lot.status(time_now), LotStatus::Cancelled);
+        assert_eq!(lot.status(to_ts(18)), LotStatus::Cancelled);
+        assert_eq!(lot.potential_claimer_id(), None, "expected no claimer");
+        assert_eq!(lot.next_bid_amount(time_now, Fraction::new(0, 1)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "cancel: wrong caller")]
+    fn test_lot_cancel_fail_wrong_caller() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.cancel(&"dan".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "cancel: expected bids, withdraw instead")]
+    fn test_lot_cancel_fail_no_bids() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.cancel(&"bob".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "cancel: expected status on sale")]
+    fn test_lot_cancel_fail_sale_success() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.cancel(&"bob".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "cancel: expected status on sale")]
+    fn test_lot_cancel_fail_already_cancelled() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_cancelled();
+        lot.cancel(&"bob".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    fn test_lot_claim_cancelled_by_seller() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_cancelled();
+        lot.claim(&"bob".parse().unwrap(), time_now);
+        assert_eq!(lot.status(time_now), LotStatus::ClaimPending);
+    }
+
+    #[test]
+    #[should_panic(expected = "claim by bidder: wrong claimer")]
+    fn test_lot_claim_cancelled_fail_by_top_bidder() {
+        let (mut lot, _) = create_lot_alice_with_bids_cancelled();
+        lot.claim(&"dan".parse().unwrap(), to_ts(18));
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: raffle cannot be reoffered by the seller")]
+    fn test_lot_validate_reoffer_fail_raffle() {
+        let (lot, time_now) = create_lot_alice_raffle();
+        lot.validate_reoffer(&"bob".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: lot is cancelled")]
+    fn test_lot_validate_reoffer_fail_cancelled() {
+        let (lot, time_now) = create_lot_alice_with_bids_cancelled();
+        lot.validate_reoffer(&"bob".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    fn test_lot_bid_retract() {
+        let (mut lot, _) = create_lot_alice();
+        let minute = to_nanos(1) / 24 / 60;
+        for (bidder_id, amount, timestamp) in
+            [("carol", "3", to_ts(11)), ("dan", "6", to_ts(11) + minute)]
+        {
+            lot.place_bid(
+                &Bid {
+                    bidder_id: bidder_id.parse().unwrap(),
+                    amount: to_yocto(amount),
+                    timestamp,
+                    public_key: None,
+                    referrer_id: None,
+                    beneficiary_id: None,
+                    rewards: BidRewards::default(),
+                    storage_usage: 0,
+                },
+                Fraction::new(0, 1),
+            );
+        }
+
+        let bid = lot.bid_retract(&"dan".parse().unwrap(), to_ts(11) + 2 * minute);
+        assert_eq!(bid.amount, to_yocto("6"), "wrong retracted bid");
+        assert_eq!(lot.bids().len(), 1, "expected one bid left");
+        assert_eq!(
+            lot.last_bid_amount(),
+            Some(to_yocto("3")),
+            "expected previous bid restored",
+        );
+
+        lot.bid_retract(&"carol".parse().unwrap(), to_ts(11) + 3 * minute);
+        assert!(lot.bids().is_empty(), "expected no bids");
+        assert!(lot.last_bid().is_none(), "expected no last bid");
+        assert_eq!(lot.status(to_ts(11) + 3 * minute), LotStatus::OnSale);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid retract: expected last bidder")]
+    fn test_lot_bid_retract_fail_wrong_bidder() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.bid_retract(&"carol".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid retract: expected last bidder")]
+    fn test_lot_bid_retract_fail_no_bids() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.bid_retract(&"carol".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid retract: retraction window expired")]
+    fn test_lot_bid_retract_fail_too_late() {
+        let (mut lot, _) = create_lot_alice_with_bids();
+        lot.finish_timestamp = to_ts(12) + 2 * LOT_BID_RETRACT_DURATION;
+        lot.bid_retract(
+            &"dan".parse().unwrap(),
+            to_ts(12) + LOT_BID_RETRACT_DURATION,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bid retract: expected status on sale")]
+    fn test_lot_bid_retract_fail_sale_success() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.bid_retract(&"dan".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    fn test_lot_validate_reoffer_success_by_seller() {
+        let caller_id: ProfileId = "bob".parse().unwrap();
+
+        let (lot, time_now) = create_lot_alice();
+        lot.validate_reoffer(&caller_id, time_now);
+
+        let (lot, time_now) = create_lot_alice_withdrawn();
+        lot.validate_reoffer(&caller_id, time_now);
+
+        let (lot, time_now) = create_lot_alice_sale_failure();
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    fn test_lot_relist() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.watch(&"carol".parse().unwrap());
+        lot.is_withdrawn = true;
+        lot.payouts = vec![("eve".parse().unwrap(), Fraction::new(1, 1))];
+
+        lot.relist(
+            "dan".parse().unwrap(),
+            to_yocto("3"),
+            to_yocto("20"),
+            time_now,
+            to_ts(30),
+        );
+        assert_eq!(lot.seller_id, "dan".parse::<ProfileId>().unwrap());
+        assert_eq!(lot.reserve_price, to_yocto("3"));
+        assert_eq!(lot.finish_timestamp, to_ts(30));
+        assert!(lot.payouts.is_empty(), "expected payouts reset");
+        assert_eq!(lot.status(time_now), LotStatus::OnSale);
+        assert_eq!(lot.watchers_count(), 1, "expected watchers kept");
+    }
+
+    #[test]
+    #[should_panic(expected = "UNREACHABLE: relist with bids left")]
+    fn test_lot_relist_fail_bids_left() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.relist(
+            "dan".parse().unwrap(),
+            to_yocto("3"),
+            to_yocto("20"),
+            time_now,
+            to_ts(30),
+        );
+    }
+
+    #[test]
+    fn test_lot_validate_reoffer_success_by_winner() {
+        let (lot, time_now) = create_lot_alice_with_bids_sale_success(); // dan is the last bidder
+        lot.validate_reoffer(&"dan".parse().unwrap(), time_now);
+
+        let (mut lot, time_now) = create_lot_alice_buy_now_bid(); // carol is the last bidder
+        lot.validate_reoffer(&"carol".parse().unwrap(), time_now);
+
+        lot.transfer_claim(&"carol".parse().unwrap(), &"eve".parse().unwrap(), time_now);
+        lot.validate_reoffer(&"eve".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: wrong caller")]
+    fn test_lot_validate_reoffer_fail_wrong_seller() {
+        let caller_id: ProfileId = "carol".parse().unwrap();
+
+        let (lot, time_now) = create_lot_alice();
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: wrong caller")]
+    fn test_lot_validate_reoffer_fail_wrong_seller_withdrawn() {
+        let caller_id: ProfileId = "carol".parse().unwrap();
+
+        let (lot, time_now) = create_lot_alice_withdrawn();
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: wrong caller")]
+    fn test_lot_validate_reoffer_fail_wrong_seller_sale_failure() {
+        let caller_id: ProfileId = "carol".parse().unwrap();
+
+        let (lot, time_now) = create_lot_alice_sale_failure();
+        lot.validate_reoffer(&caller_id, time_now);
     }
 
     #[test]
@@ -741,8 +2297,17 @@ pub mod tests {
     fn test_lot_validate_reoffer_fail_has_bids_active() {
         let caller_id: ProfileId = "bob".parse().unwrap();
 
-        let (lot, _) = create_lot_alice_with_bids();
-        lot.validate_reoffer(&caller_id);
+        let (lot, time_now) = create_lot_alice_with_bids();
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: wrong caller")]
+    fn test_lot_validate_reoffer_fail_bidder_active() {
+        let caller_id: ProfileId = "dan".parse().unwrap();
+
+        let (lot, time_now) = create_lot_alice_with_bids();
+        lot.validate_reoffer(&caller_id, time_now);
     }
 
     #[test]
@@ -750,7 +2315,257 @@ pub mod tests {
     fn test_lot_validate_reoffer_fail_has_bids_sale_success() {
         let caller_id: ProfileId = "bob".parse().unwrap();
 
-        let (lot, _) = create_lot_alice_with_bids_sale_success();
-        lot.validate_reoffer(&caller_id);
+        let (lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: wrong caller")]
+    fn test_lot_validate_reoffer_fail_not_winner_sale_success() {
+        let caller_id: ProfileId = "carol".parse().unwrap();
+
+        let (lot, time_now) = create_lot_alice_with_bids_sale_success(); // dan is the last bidder
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: wrong caller")]
+    fn test_lot_validate_reoffer_fail_winner_after_transfer() {
+        let (mut lot, time_now) = create_lot_alice_with_bids_sale_success();
+        lot.transfer_claim(&"dan".parse().unwrap(), &"eve".parse().unwrap(), time_now);
+        lot.validate_reoffer(&"dan".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: claim pending")]
+    fn test_lot_validate_reoffer_fail_claim_pending() {
+        let caller_id: ProfileId = "bob".parse().unwrap();
+
+        let (mut lot, time_now) = create_lot_alice_withdrawn();
+        lot.is_claim_pending = true;
+        lot.validate_reoffer(&caller_id, time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: claim pending")]
+    fn test_lot_validate_reoffer_fail_claim_pending_by_winner() {
+        let (lot, time_now) = create_lot_alice_with_bids_claim_pending();
+        lot.validate_reoffer(&"dan".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: lot is cleaning up")]
+    fn test_lot_validate_reoffer_fail_cleaning_up() {
+        let (lot, time_now) = create_lot_alice_with_bids_cleaning_up();
+        lot.validate_reoffer(&"dan".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    fn test_lot_bundle_join() {
+        let (lot, _) = create_lot_alice_bundle();
+        assert_eq!(
+            lot.account_ids_locked(),
+            vec![
+                "alice".parse::<LotId>().unwrap(),
+                "alice-app".parse::<LotId>().unwrap(),
+                "alice-dao".parse::<LotId>().unwrap(),
+            ],
+            "expected lot account first",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bundle join: bids exist")]
+    fn test_lot_bundle_join_fail_bids_exist() {
+        let (mut lot, time_now) = create_lot_alice_with_bids();
+        lot.bundle_join(&"alice-app".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "bundle join: seller and lot cannot join")]
+    fn test_lot_bundle_join_fail_lot_itself() {
+        let (mut lot, time_now) = create_lot_alice();
+        lot.bundle_join(&"alice".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "bundle join: already joined")]
+    fn test_lot_bundle_join_fail_already_joined() {
+        let (mut lot, time_now) = create_lot_alice_bundle();
+        lot.bundle_join(&"alice-app".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "bundle join: too many accounts")]
+    fn test_lot_bundle_join_fail_too_many() {
+        let (mut lot, time_now) = create_lot_alice();
+        for i in 0..=LOT_BUNDLE_MAX_LEN {
+            let account_id: LotId = format!("alice-{}", i).parse().unwrap();
+            lot.bundle_join(&account_id, time_now);
+        }
+    }
+
+    #[test]
+    fn test_lot_claim_unlocked_partially() {
+        let (mut lot, time_now) = create_lot_alice_bundle_with_bids_claim_pending();
+
+        let result =
+            lot.claim_unlocked(vec!["alice".parse().unwrap(), "alice-dao".parse().unwrap()]);
+        assert!(!result, "expected not all accounts unlocked");
+        assert_eq!(
+            lot.status(time_now),
+            LotStatus::SaleSuccess,
+            "expected claim reverted"
+        );
+        assert_eq!(
+            lot.account_ids_locked(),
+            vec!["alice-app".parse::<LotId>().unwrap()],
+            "expected only failed account locked",
+        );
+
+        lot.claim(&"carol".parse().unwrap(), time_now);
+        let result = lot.claim_unlocked(vec!["alice-app".parse().unwrap()]);
+        assert!(result, "expected all accounts unlocked");
+        assert!(lot.account_ids_locked().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "transfer claim: bundle partially unlocked")]
+    fn test_lot_transfer_claim_fail_partially_unlocked() {
+        let (mut lot, time_now) = create_lot_alice_bundle_with_bids_claim_pending();
+        lot.claim_unlocked(vec!["alice".parse().unwrap()]);
+        lot.transfer_claim(&"carol".parse().unwrap(), &"eve".parse().unwrap(), time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "reoffer: bundle partially unlocked")]
+    fn test_lot_validate_reoffer_fail_partially_unlocked() {
+        let (mut lot, time_now) = create_lot_alice_bundle_with_bids_claim_pending();
+        lot.claim_unlocked(vec!["alice".parse().unwrap()]);
+        lot.validate_reoffer(&"carol".parse().unwrap(), time_now);
+    }
+
+    fn create_ticket(bidder_id: &str) -> Bid {
+        Bid {
+            bidder_id: bidder_id.parse().unwrap(),
+            amount: to_yocto("2"),
+            timestamp: to_ts(14),
+            public_key: None,
+            referrer_id: None,
+            beneficiary_id: None,
+            rewards: BidRewards::default(),
+            storage_usage: 0,
+        }
+    }
+
+    #[test]
+    fn test_lot_buy_ticket() {
+        let (lot, time_now) = create_lot_alice_raffle_with_tickets();
+        assert_eq!(lot.bids_count(), 3, "wrong tickets count");
+        assert_eq!(
+            lot.status(time_now),
+            LotStatus::OnSale,
+            "expected tickets not to finish the raffle"
+        );
+        assert!(lot.has_ticket(&"dan".parse().unwrap()));
+        assert!(!lot.has_ticket(&"eve".parse().unwrap()));
+        assert_eq!(
+            lot.next_bid_amount(time_now, Fraction::new(1, 5)),
+            Some(to_yocto("2")),
+            "expected ticket price"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ticket: expected raffle lot")]
+    fn test_lot_buy_ticket_fail_not_raffle() {
+        let (mut lot, _) = create_lot_alice();
+        lot.buy_ticket(&create_ticket("eve"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ticket: expected ticket price")]
+    fn test_lot_buy_ticket_fail_wrong_price() {
+        let (mut lot, _) = create_lot_alice_raffle();
+        let mut ticket = create_ticket("eve");
+        ticket.amount = to_yocto("3");
+        lot.buy_ticket(&ticket);
+    }
+
+    #[test]
+    #[should_panic(expected = "ticket: already holds a ticket")]
+    fn test_lot_buy_ticket_fail_already_bought() {
+        let (mut lot, _) = create_lot_alice_raffle_with_tickets();
+        lot.buy_ticket(&create_ticket("dan"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ticket: seller and lot cannot buy")]
+    fn test_lot_buy_ticket_fail_seller() {
+        let (mut lot, _) = create_lot_alice_raffle();
+        lot.buy_ticket(&create_ticket("bob"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ticket: expected status active")]
+    fn test_lot_buy_ticket_fail_finished() {
+        let (mut lot, _) = create_lot_alice_raffle();
+        let mut ticket = create_ticket("eve");
+        ticket.timestamp = to_ts(18);
+        lot.buy_ticket(&ticket);
+    }
+
+    #[test]
+    #[should_panic(expected = "bid: raffle lot, buy a ticket instead")]
+    fn test_lot_place_bid_fail_raffle() {
+        let (mut lot, _) = create_lot_alice_raffle();
+        lot.place_bid(&create_ticket("eve"), Fraction::new(1, 5));
+    }
+
+    #[test]
+    fn test_lot_draw() {
+        let (mut lot, _) = create_lot_alice_raffle_with_tickets();
+        let time_now = to_ts(18);
+        assert_eq!(lot.status(time_now), LotStatus::DrawPending);
+        assert_eq!(lot.potential_claimer_id(), None, "expected no claimer");
+
+        let mut seed = vec![0u8; 32];
+        seed[0] = 4;
+        let (winning_ticket, refunds) = lot.draw(&seed, time_now);
+        let dan: ProfileId = "dan".parse().unwrap();
+        assert_eq!(winning_ticket.bidder_id, dan, "expected ticket 4 % 3");
+        let refund_ids: Vec<ProfileId> = refunds.into_iter().map(|x| x.bidder_id).collect();
+        assert_eq!(
+            refund_ids,
+            vec![
+                "carol".parse::<ProfileId>().unwrap(),
+                "frank".parse::<ProfileId>().unwrap(),
+            ],
+            "expected other tickets refunded",
+        );
+        assert_eq!(lot.status(time_now), LotStatus::SaleSuccess);
+        assert_eq!(lot.potential_claimer_id(), Some(dan));
+    }
+
+    #[test]
+    #[should_panic(expected = "draw: expected status draw pending")]
+    fn test_lot_draw_fail_on_sale() {
+        let (mut lot, time_now) = create_lot_alice_raffle_with_tickets();
+        lot.draw(&[0u8; 32], time_now);
+    }
+
+    #[test]
+    #[should_panic(expected = "draw: expected status draw pending")]
+    fn test_lot_draw_fail_already_drawn() {
+        let (mut lot, _) = create_lot_alice_raffle_with_tickets();
+        let time_now = to_ts(18);
+        lot.draw(&[0u8; 32], time_now);
+        lot.draw(&[0u8; 32], time_now);
+    }
+
+    #[test]
+    fn test_lot_status_raffle_without_tickets() {
+        let (lot, _) = create_lot_alice_raffle();
+        assert_eq!(lot.status(to_ts(18)), LotStatus::SaleFailure);
     }
 }
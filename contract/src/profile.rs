@@ -1,13 +1,17 @@
 use crate::*;
 
+pub const ERR_PROFILE_REWARDS_WITHDRAW_NOT_ENOUGH: &str = "rewards withdraw: not enough rewards";
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Profile {
     pub profile_id: ProfileId,
     rewards_available: Balance,
     rewards_claimed: Balance,
+    referral_rewards_total: Balance,
 
     pub lots_offering: UnorderedSet<LotId>,
     pub lots_bidding: UnorderedSet<LotId>,
+    pub lots_watching: UnorderedSet<LotId>,
 }
 
 impl Profile {
@@ -20,12 +24,18 @@ impl Profile {
         prefix_bidding.extend(PREFIX_PROFILE_LOTS_BIDDING.as_bytes());
         prefix_bidding.extend(env::sha256(profile_id.as_bytes()));
 
+        let mut prefix_watching: Vec<u8> = Vec::with_capacity(33);
+        prefix_watching.extend(PREFIX_PROFILE_LOTS_WATCHING.as_bytes());
+        prefix_watching.extend(env::sha256(profile_id.as_bytes()));
+
         Profile {
             profile_id: profile_id.clone(),
             rewards_available: 0,
             rewards_claimed: 0,
+            referral_rewards_total: 0,
             lots_offering: UnorderedSet::new(prefix_offering),
             lots_bidding: UnorderedSet::new(prefix_bidding),
+            lots_watching: UnorderedSet::new(prefix_watching),
         }
     }
 
@@ -33,6 +43,26 @@ impl Profile {
         self.rewards_available += amount;
     }
 
+    pub fn referral_rewards_transfer(&mut self, amount: Balance) {
+        self.rewards_transfer(amount);
+        self.referral_rewards_total += amount;
+    }
+
+    // takes back previously transferred rewards, fails if they were already claimed
+    pub fn rewards_withdraw(&mut self, amount: Balance) {
+        assert!(
+            self.rewards_available >= amount,
+            "{}",
+            ERR_PROFILE_REWARDS_WITHDRAW_NOT_ENOUGH,
+        );
+        self.rewards_available -= amount;
+    }
+
+    pub fn referral_rewards_withdraw(&mut self, amount: Balance) {
+        self.rewards_withdraw(amount);
+        self.referral_rewards_total -= amount;
+    }
+
     pub fn rewards_claim(&mut self) -> Balance {
         let amount = self.rewards_available;
         self.rewards_available -= amount;
@@ -53,6 +83,10 @@ impl Profile {
     pub fn rewards_claimed(&self) -> Balance {
         self.rewards_claimed
     }
+
+    pub fn referral_rewards_total(&self) -> Balance {
+        self.referral_rewards_total
+    }
 }
 
 #[cfg(test)]
@@ -101,6 +135,52 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_profile_rewards_withdraw() {
+        let mut profile = create_profile_bob();
+
+        profile.rewards_withdraw(to_yocto("1"));
+        assert_eq!(
+            profile.rewards_available,
+            to_yocto("2"),
+            "wrong rewards_available"
+        );
+        assert_eq!(
+            profile.rewards_claimed,
+            to_yocto("2"),
+            "expected rewards_claimed unchanged"
+        );
+
+        profile.referral_rewards_transfer(to_yocto("1"));
+        profile.referral_rewards_withdraw(to_yocto("1"));
+        assert_eq!(profile.referral_rewards_total, 0, "wrong referral total");
+    }
+
+    #[test]
+    #[should_panic(expected = "rewards withdraw: not enough rewards")]
+    fn test_profile_rewards_withdraw_fail_not_enough() {
+        let mut profile = create_profile_bob();
+        profile.rewards_withdraw(to_yocto("3") + 1);
+    }
+
+    #[test]
+    fn test_profile_referral_rewards_transfer() {
+        let mut profile = create_profile_bob();
+
+        profile.referral_rewards_transfer(to_yocto("1"));
+        profile.referral_rewards_transfer(to_yocto("2"));
+        assert_eq!(
+            profile.rewards_available,
+            to_yocto("6"),
+            "wrong rewards_available"
+        );
+        assert_eq!(
+            profile.referral_rewards_total,
+            to_yocto("3"),
+            "wrong referral_rewards_total"
+        );
+    }
+
     #[test]
     fn test_profile_rewards_claim() {
         let mut profile = create_profile_bob();
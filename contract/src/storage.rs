@@ -0,0 +1,114 @@
+use crate::*;
+
+pub const ERR_STORAGE_NOT_ENOUGH_BALANCE: &str = "storage: not enough storage balance";
+pub const ERR_STORAGE_WITHDRAW_TOO_MUCH: &str = "storage: withdraw exceeds available balance";
+pub const ERR_STORAGE_RELEASE_TOO_MUCH: &str = "storage: released more than used";
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct StorageAccount {
+    total: Balance,
+    // bytes paid from the total
+    used: StorageUsage,
+}
+
+impl StorageAccount {
+    pub fn total(&self) -> Balance {
+        self.total
+    }
+
+    pub fn used(&self) -> StorageUsage {
+        self.used
+    }
+
+    pub fn used_balance(&self) -> Balance {
+        Balance::from(self.used) * env::storage_byte_cost()
+    }
+
+    pub fn available(&self) -> Balance {
+        self.total - self.used_balance()
+    }
+
+    pub fn deposit(&mut self, amount: Balance) {
+        self.total += amount;
+    }
+
+    pub fn withdraw(&mut self, amount: Balance) {
+        assert!(
+            amount <= self.available(),
+            "{}",
+            ERR_STORAGE_WITHDRAW_TOO_MUCH,
+        );
+        self.total -= amount;
+    }
+
+    pub fn charge(&mut self, bytes: StorageUsage) {
+        self.used += bytes;
+        assert!(
+            self.used_balance() <= self.total,
+            "{}",
+            ERR_STORAGE_NOT_ENOUGH_BALANCE,
+        );
+    }
+
+    pub fn release(&mut self, bytes: StorageUsage) {
+        self.used = self
+            .used
+            .checked_sub(bytes)
+            .expect(ERR_STORAGE_RELEASE_TOO_MUCH);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::tests::*;
+
+    #[test]
+    fn test_storage_account_charge_release() {
+        testing_env!(get_context_view(to_ts(10)));
+        let byte_cost = env::storage_byte_cost();
+        let mut account = StorageAccount::default();
+        account.deposit(byte_cost * 100);
+
+        account.charge(60);
+        assert_eq!(account.used(), 60, "wrong used");
+        assert_eq!(account.available(), byte_cost * 40, "wrong available");
+
+        account.release(20);
+        assert_eq!(account.used(), 40, "wrong used");
+        assert_eq!(account.available(), byte_cost * 60, "wrong available");
+
+        account.withdraw(byte_cost * 60);
+        assert_eq!(account.total(), byte_cost * 40, "wrong total");
+        assert_eq!(account.available(), 0, "wrong available");
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: not enough storage balance")]
+    fn test_storage_account_charge_fail_not_enough_balance() {
+        testing_env!(get_context_view(to_ts(10)));
+        let mut account = StorageAccount::default();
+        account.deposit(env::storage_byte_cost() * 100);
+        account.charge(101);
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: released more than used")]
+    fn test_storage_account_release_fail_negative() {
+        testing_env!(get_context_view(to_ts(10)));
+        let mut account = StorageAccount::default();
+        account.deposit(env::storage_byte_cost() * 100);
+        account.charge(10);
+        account.release(11);
+    }
+
+    #[test]
+    #[should_panic(expected = "storage: withdraw exceeds available balance")]
+    fn test_storage_account_withdraw_fail_used() {
+        testing_env!(get_context_view(to_ts(10)));
+        let byte_cost = env::storage_byte_cost();
+        let mut account = StorageAccount::default();
+        account.deposit(byte_cost * 100);
+        account.charge(10);
+        account.withdraw(byte_cost * 91);
+    }
+}
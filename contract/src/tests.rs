@@ -1,7 +1,7 @@
 pub use super::*;
 
 pub use near_sdk::test_utils::VMContextBuilder;
-pub use near_sdk::{testing_env, VMContext};
+pub use near_sdk::{testing_env, RuntimeFeesConfig, VMConfig, VMContext};
 pub use near_sdk_sim::{to_nanos, to_ts, to_yocto};
 
 pub fn get_context_view(time_now: Timestamp) -> VMContext {
@@ -32,10 +32,34 @@ pub fn get_context_pay(
         .build()
 }
 
+pub fn set_context_callback(time_now: Timestamp, promise_results: Vec<PromiseResult>) {
+    let marketplace: AccountId = "marketplace".parse().unwrap();
+    testing_env!(
+        VMContextBuilder::new()
+            .current_account_id(marketplace.clone())
+            .predecessor_account_id(marketplace)
+            .block_timestamp(time_now)
+            .build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        promise_results,
+    );
+}
+
 pub fn build_contract() -> Contract {
     Contract::new(
         FractionView { num: 1, denom: 10 },
         FractionView { num: 1, denom: 5 },
         FractionView { num: 4, denom: 5 },
+        Some(FractionView { num: 1, denom: 2 }),
+        None,
+        Some(FractionView { num: 1, denom: 10 }),
+        Some(FractionView { num: 9, denom: 10 }),
+        Some(FractionView { num: 1, denom: 10 }),
+        Some(FractionView { num: 1, denom: 2 }),
+        Some(to_yocto("1").into()),
+        None,
+        None,
     )
 }
@@ -8,3 +8,14 @@ pub fn is_promise_success() -> bool {
     );
     matches!(env::promise_result(0), PromiseResult::Successful(_))
 }
+
+// unlock results of the promises joined with and, in the same order. The lock contract
+// returns false if the key wasn't added, older versions of it return nothing
+pub fn promise_results_unlocked() -> Vec<bool> {
+    (0..env::promise_results_count())
+        .map(|index| match env::promise_result(index) {
+            PromiseResult::Successful(x) => x != b"false",
+            _ => false,
+        })
+        .collect()
+}
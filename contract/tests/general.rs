@@ -1,8 +1,9 @@
-use near_sdk::serde_json::json;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde_json::{self, json};
 use near_sdk::{Balance, Timestamp};
 use near_sdk_sim::{
-    call, deploy, init_simulator, to_nanos, to_ts, to_yocto, view, ContractAccount, UserAccount,
-    DEFAULT_GAS, STORAGE_AMOUNT,
+    call, deploy, init_simulator, to_nanos, to_ts, to_yocto, view, ContractAccount,
+    ExecutionResult, UserAccount, DEFAULT_GAS, STORAGE_AMOUNT,
 };
 
 use marketplace::{
@@ -44,7 +45,16 @@ fn init() -> (UserAccount, ContractAccount<ContractContract>) {
         init_method: new(
             FractionView { num: 1, denom: 8 },
             FractionView { num: 1, denom: 4 },
-            FractionView { num: 0, denom: 1 }
+            FractionView { num: 0, denom: 1 },
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None
         ),
     );
 
@@ -94,6 +104,78 @@ fn create_user_locked_with_owner(root: &UserAccount, name: &str, owner_id: &str)
     alice
 }
 
+fn create_user_locked_and_offered(
+    root: &UserAccount,
+    name: &str,
+    seller_id: &str,
+    reserve_price: Balance,
+) -> (UserAccount, ExecutionResult) {
+    let lot = root.deploy(
+        &LOCK_CONTRACT_BYTES,
+        name.parse().unwrap(),
+        STORAGE_AMOUNT, // attached deposit
+    );
+    let result = lot.call(
+        "marketplace".parse().unwrap(),
+        "storage_deposit",
+        &json!({ "account_id": seller_id.to_string() })
+            .to_string()
+            .into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("0.1"),
+    );
+    assert!(result.is_ok());
+    let result = lot.call(
+        lot.account_id(),
+        "lock",
+        &json!({
+            "owner_id": "marketplace".to_string(),
+            "lot": {
+                "seller_id": seller_id.to_string(),
+                "reserve_price": reserve_price.to_string(),
+                "buy_now_price": to_yocto("10").to_string(),
+                "duration": to_nanos(7).to_string(),
+            },
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+
+    (lot, result)
+}
+
+fn create_user_locked_and_bundled(
+    root: &UserAccount,
+    name: &str,
+    lot_id: &str,
+    seller_id: &str,
+) -> (UserAccount, ExecutionResult) {
+    let account = root.deploy(
+        &LOCK_CONTRACT_BYTES,
+        name.parse().unwrap(),
+        STORAGE_AMOUNT, // attached deposit
+    );
+    let result = account.call(
+        account.account_id(),
+        "lock",
+        &json!({
+            "owner_id": "marketplace".to_string(),
+            "bundle": {
+                "lot_id": lot_id.to_string(),
+                "seller_id": seller_id.to_string(),
+            },
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+
+    (account, result)
+}
+
 fn create_user_locked(root: &UserAccount, name: &str) -> UserAccount {
     create_user_locked_with_owner(root, name, "marketplace")
 }
@@ -106,10 +188,28 @@ fn set_timestamp(root: &UserAccount, timestamp: Timestamp) {
     root.borrow_runtime_mut().cur_block.block_timestamp = timestamp;
 }
 
+fn m_storage_deposit(contract: &ContractAccount<ContractContract>, account: &UserAccount) {
+    let result = call!(
+        account,
+        contract.storage_deposit(None, None),
+        deposit = to_yocto("0.1")
+    );
+    assert!(result.is_ok());
+}
+
 fn m_lot_offer(
     contract: &ContractAccount<ContractContract>,
     lot: &UserAccount,
     seller: &UserAccount,
+) {
+    m_lot_offer_with_sweep(contract, lot, seller, None);
+}
+
+fn m_lot_offer_with_sweep(
+    contract: &ContractAccount<ContractContract>,
+    lot: &UserAccount,
+    seller: &UserAccount,
+    sweep_on_claim: Option<bool>,
 ) {
     let reserve_price = to_yocto("3");
     let buy_now_price = to_yocto("10");
@@ -117,6 +217,7 @@ fn m_lot_offer(
     set_timestamp(&lot, start_timestamp);
     let finish_timestamp = start_timestamp + to_nanos(7);
 
+    m_storage_deposit(contract, seller);
     let result = call!(
         lot,
         contract.lot_offer(
@@ -124,6 +225,12 @@ fn m_lot_offer(
             reserve_price.into(),
             buy_now_price.into(),
             Some(finish_timestamp.into()),
+            None,
+            sweep_on_claim,
+            None,
+            None,
+            None,
+            None,
             None
         )
     );
@@ -146,10 +253,11 @@ fn simulate_lot_offer_buy_now() {
     bob.transfer(root.account_id(), to_yocto("100")); // storage and future gas
 
     m_lot_offer(&contract, &alice, &bob);
+    m_storage_deposit(&contract, &carol);
 
     let result = call!(
         carol,
-        contract.lot_bid(alice.account_id.clone()),
+        contract.lot_bid(alice.account_id.clone(), None, None, None),
         deposit = to_yocto("10")
     );
     assert!(result.is_ok());
@@ -173,7 +281,7 @@ fn simulate_lot_offer_buy_now() {
 
     let result = call!(
         carol,
-        contract.lot_claim(alice.account_id(), NEW_PUBLIC_KEY.parse().unwrap())
+        contract.lot_claim(alice.account_id(), NEW_PUBLIC_KEY.parse().unwrap(), None)
     );
     assert!(result.is_ok());
 
@@ -220,6 +328,143 @@ fn simulate_lot_offer_buy_now() {
     bob.transfer(root.account_id(), seller_rewards);
 }
 
+#[test]
+fn simulate_lot_finalize() {
+    let (root, contract) = init();
+    let alice: UserAccount = create_user_locked(&root, "alice");
+    let bob: UserAccount = create_user(&root, "bob");
+    let carol: UserAccount = create_user(&root, "carol");
+    let keeper: UserAccount = create_user(&root, "keeper");
+
+    m_lot_offer(&contract, &alice, &bob);
+    m_storage_deposit(&contract, &carol);
+
+    let result = call!(
+        carol,
+        contract.lot_bid(
+            alice.account_id.clone(),
+            Some(NEW_PUBLIC_KEY.parse().unwrap()),
+            None,
+            None
+        ),
+        deposit = to_yocto("10")
+    );
+    assert!(result.is_ok());
+
+    let result = call!(keeper, contract.lot_finalize(alice.account_id()));
+    assert!(result.is_ok());
+
+    let result = view!(contract.lot_list(None, None));
+    let result: Vec<LotView> = result.unwrap_json();
+    assert!(result.is_empty(), "expected lot to be cleaned up");
+
+    let result = view!(contract.lot_list_bidding_by(carol.account_id(), None, None));
+    let result: Vec<LotView> = result.unwrap_json();
+    assert!(
+        result.is_empty(),
+        "expected empty lot list bidding after finalize"
+    );
+}
+
+#[test]
+fn simulate_lot_claim_many() {
+    let (root, contract) = init();
+    let alice: UserAccount = create_user_locked(&root, "alice");
+    let dan: UserAccount = create_user_locked(&root, "dan");
+    let bob: UserAccount = create_user(&root, "bob");
+    let carol: UserAccount = create_user(&root, "carol");
+
+    m_storage_deposit(&contract, &carol);
+    for lot in [&alice, &dan] {
+        m_lot_offer(&contract, lot, &bob);
+        let result = call!(
+            carol,
+            contract.lot_bid(lot.account_id.clone(), None, None, None),
+            deposit = to_yocto("10")
+        );
+        assert!(result.is_ok());
+    }
+
+    let result = call!(
+        carol,
+        contract.lot_claim_many(vec![
+            (alice.account_id(), NEW_PUBLIC_KEY.parse().unwrap()),
+            (dan.account_id(), NEW_PUBLIC_KEY.parse().unwrap()),
+        ])
+    );
+    assert!(result.is_ok());
+    let logs = all_logs(&result);
+    for lot in [&alice, &dan] {
+        let expected = format!("lot_after_claim_clean_up: {} claimed", lot.account_id);
+        assert!(
+            logs.contains(&expected),
+            "expected {} claimed",
+            lot.account_id
+        );
+    }
+
+    let result = view!(contract.lot_list(None, None));
+    let result: Vec<LotView> = result.unwrap_json();
+    assert!(result.is_empty(), "expected lots to be cleaned up");
+}
+
+fn all_logs(result: &ExecutionResult) -> Vec<String> {
+    result
+        .promise_results()
+        .into_iter()
+        .flatten()
+        .flat_map(|x| x.logs().clone())
+        .collect()
+}
+
+#[test]
+fn simulate_lot_claim_sweep_on_claim() {
+    let (root, contract) = init();
+    let alice: UserAccount = create_user_locked(&root, "alice");
+    let bob: UserAccount = create_user(&root, "bob");
+    let carol: UserAccount = create_user(&root, "carol");
+
+    m_lot_offer_with_sweep(&contract, &alice, &bob, Some(true));
+    m_storage_deposit(&contract, &carol);
+
+    let result = call!(
+        carol,
+        contract.lot_bid(alice.account_id.clone(), None, None, None),
+        deposit = to_yocto("10")
+    );
+    assert!(result.is_ok());
+
+    let bob_balance_before = bob.account().unwrap().amount;
+    let result = call!(
+        carol,
+        contract.lot_claim(alice.account_id(), NEW_PUBLIC_KEY.parse().unwrap(), None)
+    );
+    assert!(result.is_ok());
+    assert!(
+        all_logs(&result)
+            .iter()
+            .any(|x| x.starts_with("sweep_to: swept ")),
+        "expected swept amount in logs"
+    );
+    assert!(
+        all_logs(&result)
+            .iter()
+            .any(|x| x.starts_with("lot_after_claim_sweep: swept ")),
+        "expected swept amount in marketplace logs"
+    );
+
+    let bob_balance_after = bob.account().unwrap().amount;
+    assert!(
+        bob_balance_after - bob_balance_before > to_yocto("40"),
+        "expected lot balance to be swept to seller, got {}",
+        from_yocto(bob_balance_after - bob_balance_before),
+    );
+
+    let result = view!(contract.lot_list(None, None));
+    let result: Vec<LotView> = result.unwrap_json();
+    assert!(result.is_empty(), "expected lot to be cleaned up");
+}
+
 #[test]
 fn simulate_lot_offer_withdraw() {
     let (root, contract) = init();
@@ -251,7 +496,7 @@ fn simulate_lot_offer_withdraw() {
 
     let result = call!(
         bob,
-        contract.lot_claim(alice.account_id(), NEW_PUBLIC_KEY.parse().unwrap())
+        contract.lot_claim(alice.account_id(), NEW_PUBLIC_KEY.parse().unwrap(), None)
     );
     assert!(result.is_ok());
 
@@ -301,7 +546,7 @@ fn simulate_lot_remove_unsafe_success_no_lock() {
 
     let result = call!(carol, contract.lot_remove_unsafe(alice.account_id.clone()));
     let expected_message = "lot_after_remove_unsafe_remove: promise_unsuccessful";
-    assert!(result.logs().contains(&expected_message.to_string()));
+    assert!(all_logs(&result).contains(&expected_message.to_string()));
     assert!(result.is_ok());
 
     let result = view!(contract.lot_list(None, None));
@@ -381,6 +626,21 @@ fn simulate_lock_unlock() {
         .unwrap_json();
     assert_eq!(result, "alice".to_string(), "expected owner alice");
 
+    let result: serde_json::Value = root
+        .view(
+            contract.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(result["owner_id"], "alice", "expected owner alice");
+    assert_eq!(
+        result["original_owner_id"], "alice",
+        "expected original owner alice"
+    );
+    assert_eq!(result["is_unlocked"], false, "expected locked");
+    assert_eq!(result["unlocks"], json!([]), "expected no unlocks");
+
     let result = alice.call(
         contract.account_id(),
         "unlock",
@@ -393,4 +653,254 @@ fn simulate_lock_unlock() {
         0,
     );
     assert!(result.is_ok());
+
+    let result: serde_json::Value = root
+        .view(
+            contract.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        result["owner_id"], "locked",
+        "expected owner to be the account itself"
+    );
+    assert_eq!(
+        result["original_owner_id"], "alice",
+        "expected original owner alice"
+    );
+    assert_eq!(result["is_unlocked"], true, "expected unlocked");
+    assert_eq!(
+        result["unlocks"][0]["owner_id"], "alice",
+        "expected unlock by alice"
+    );
+}
+
+fn lock_to_alice_and_unlock_with_code(new_code: &[u8]) -> (UserAccount, UserAccount) {
+    let (root, contract, alice) = init_locked();
+
+    let result = contract.call(
+        contract.account_id(),
+        "lock",
+        &json!({
+            "owner_id": "alice".to_string(),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(result.is_ok());
+
+    let result = alice.call(
+        contract.account_id(),
+        "unlock",
+        &json!({
+            "public_key": NEW_PUBLIC_KEY.to_string(),
+            "new_code": Base64VecU8::from(new_code.to_vec()),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(result.is_ok());
+
+    (root, contract)
+}
+
+#[test]
+fn simulate_lock_unlock_with_code_fail_reported() {
+    let (_, contract, alice) = init_locked();
+
+    let result = contract.call(
+        contract.account_id(),
+        "lock",
+        &json!({
+            "owner_id": "alice".to_string(),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(result.is_ok());
+
+    // the key already exists, the whole batch fails
+    let result = alice.call(
+        contract.account_id(),
+        "unlock",
+        &json!({
+            "public_key": contract.signer.public_key.to_string(),
+            "new_code": Base64VecU8::from(vec![]),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(!result.is_ok(), "expected failed batch to fail unlock");
+
+    let result: serde_json::Value = contract
+        .view(
+            contract.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        result["owner_id"], "alice",
+        "expected lock to stay in place"
+    );
+}
+
+#[test]
+fn simulate_lock_unlock_remove_code() {
+    let (root, contract) = lock_to_alice_and_unlock_with_code(&[]);
+
+    let result = root.view(
+        contract.account_id(),
+        "get_owner",
+        &json!({}).to_string().into_bytes(),
+    );
+    assert!(result.is_err(), "expected lock code to be removed");
+}
+
+#[test]
+fn simulate_lock_unlock_replace_code_clears_state() {
+    let (root, contract) = lock_to_alice_and_unlock_with_code(LOCK_CONTRACT_BYTES);
+
+    // same code is redeployed, so the only reason for the view to fail is missing state
+    let result = root.view(
+        contract.account_id(),
+        "get_owner",
+        &json!({}).to_string().into_bytes(),
+    );
+    assert!(result.is_err(), "expected lock state to be cleared");
+
+    let result = contract.call(
+        contract.account_id(),
+        "lock",
+        &json!({
+            "owner_id": "alice".to_string(),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(result.is_ok(), "expected account to be lockable again");
+}
+
+#[test]
+fn simulate_lock_and_offer_success() {
+    let (root, contract) = init();
+    let (alice, result) = create_user_locked_and_offered(&root, "alice", "bob", to_yocto("3"));
+    assert!(result.is_ok());
+
+    let result: Vec<LotView> = view!(contract.lot_list(None, None)).unwrap_json();
+    assert_eq!(result.len(), 1, "expected lot to be offered");
+    let result = &result[0];
+    assert_eq!(result.lot_id, alice.account_id(), "wrong lot_id");
+    assert_eq!(result.seller_id, "bob".parse().unwrap(), "wrong seller_id");
+    assert_eq!(
+        result.reserve_price,
+        to_yocto("3").into(),
+        "wrong reserve_price"
+    );
+
+    let result: serde_json::Value = root
+        .view(
+            alice.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        result["owner_id"], "marketplace",
+        "expected marketplace owner"
+    );
+}
+
+#[test]
+fn simulate_lock_and_offer_fail_reverts_lock() {
+    let (root, contract) = init();
+    // reserve price is greater than buy now price, lot_offer fails
+    let (alice, result) = create_user_locked_and_offered(&root, "alice", "bob", to_yocto("11"));
+    assert!(result.is_ok());
+    assert!(
+        all_logs(&result)
+            .iter()
+            .any(|x| x == "on_lot_offered: lot offer failed, owner is bob"),
+        "expected lot offer failure to be logged"
+    );
+
+    let result: Vec<LotView> = view!(contract.lot_list(None, None)).unwrap_json();
+    assert!(result.is_empty(), "expected no lots");
+
+    let result: serde_json::Value = root
+        .view(
+            alice.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        result["owner_id"], "bob",
+        "expected ownership passed to seller"
+    );
+}
+
+#[test]
+fn simulate_lock_and_bundle_join_success() {
+    let (root, contract) = init();
+    let (alice, result) = create_user_locked_and_offered(&root, "alice", "bob", to_yocto("3"));
+    assert!(result.is_ok());
+    let (alice_app, result) = create_user_locked_and_bundled(&root, "alice-app", "alice", "bob");
+    assert!(result.is_ok());
+
+    let result: LotView = view!(contract.lot_get(alice.account_id())).unwrap_json();
+    assert_eq!(
+        result.bundle_ids,
+        vec![alice_app.account_id()],
+        "expected account joined"
+    );
+
+    let result: serde_json::Value = root
+        .view(
+            alice_app.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        result["owner_id"], "marketplace",
+        "expected marketplace owner"
+    );
+}
+
+#[test]
+fn simulate_lock_and_bundle_join_fail_reverts_lock() {
+    let (root, _contract) = init();
+    // no lot to join
+    let (alice_app, result) = create_user_locked_and_bundled(&root, "alice-app", "alice", "bob");
+    assert!(result.is_ok());
+    assert!(
+        all_logs(&result)
+            .iter()
+            .any(|x| x == "on_bundle_joined: bundle join failed, owner is bob"),
+        "expected bundle join failure to be logged"
+    );
+
+    let result: serde_json::Value = root
+        .view(
+            alice_app.account_id(),
+            "get_lock_info",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        result["owner_id"], "bob",
+        "expected ownership passed to seller"
+    );
 }
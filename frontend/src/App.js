@@ -8,7 +8,7 @@ import Lots from './components/Lots';
 import ProfilePage from './components/Profile';
 import LogoutIcon from '@mui/icons-material/Logout';
 import CreateOffer from "./components/CreateOffer";
-import {nearToFloor, renderName, withTimeout} from "./utils";
+import {callWithStorageDeposit, nearToFloor, renderName, withTimeout} from "./utils";
 import AboutPage from "./components/About";
 import ConfirmContextProvider from "./Providers/ConfirmContextProvider";
 import ModalConfirm from "./components/Confirm";
@@ -156,7 +156,8 @@ function App (props) {
       const lot = await withTimeout(props.contract.lot_get({lot_id: lotAccountId}))
 
       if (!lot) {
-        await withTimeout(props.contract.lot_offer(offerData));
+        // lot storage is charged to the seller, registered by the lot account if needed
+        await withTimeout(callWithStorageDeposit(props.contract, offerData.seller_id, 'lot_offer', offerData));
       }
 
       for (let index = 0; index < accessKeys.length; index++) {
@@ -7,6 +7,7 @@ import {
   toNear,
   getBuyNowPrice,
   getNextBidAmount,
+  callWithStorageDeposit,
 } from "../utils";
 import ModalAlert from "./Alert";
 import { useHistory } from "react-router-dom";
@@ -135,12 +136,14 @@ function LotsList(props) {
       }
     }
 
-    await contract.lot_bid({
-      args: { lot_id: lot.lot_id },
-      gas: BOATLOAD_OF_GAS,
-      amount: bid_price.toFixed(),
-      callbackUrl: new URL('/#/profile', window.location.origin),
-    });
+    await callWithStorageDeposit(
+      contract,
+      signedAccount,
+      'lot_bid',
+      { lot_id: lot.lot_id },
+      bid_price.toFixed(),
+      new URL('/#/profile', window.location.origin),
+    );
   };
 
   useEffect( () => {
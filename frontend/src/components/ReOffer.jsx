@@ -3,6 +3,7 @@ import {
   toNear,
   nearToFloor,
   MIN_RESERVE_PRICE, renderName,
+  callWithStorageDeposit,
 } from '../utils.js';
 import {Box, FormControl, FormHelperText, IconButton, InputLabel, MenuItem, Modal, Select} from "@mui/material";
 import { useForm } from "react-hook-form";
@@ -74,7 +75,7 @@ function Offer (props) {
     };
 
     setShowLoader(true);
-    await props.contract.lot_reoffer(offerData);
+    await callWithStorageDeposit(props.contract, props.contract.account.accountId, 'lot_reoffer', offerData);
     await props.getLot(lot_id);
     setShowLoader(false);
     setShowSuccess(true);
@@ -1,4 +1,5 @@
 import Big from 'big.js'
+import BN from 'bn.js';
 import * as nearAPI from 'near-api-js';
 import React from "react";
 import getConfig from "./config";
@@ -8,6 +9,10 @@ export const CONFIG = getConfig(process.env.NETWORK_ID || 'testnet');
 export const ACCESS_KEY_ALLOWANCE = Big(1000000000).times(10 ** 24).toFixed();
 export const MAX_UINT8 = '340282366920938463463374607431768211455';
 export const BOATLOAD_OF_GAS = Big(3).times(10 ** 14).toFixed();
+export const STORAGE_DEPOSIT_GAS = Big(1).times(10 ** 13).toFixed();
+// offers, bids and reoffers are paid from the marketplace storage balance
+export const STORAGE_DEPOSIT_AMOUNT = Big(5).times(10 ** 22).toFixed();
+export const STORAGE_AVAILABLE_MIN = Big(1).times(10 ** 22).toFixed();
 
 Big.DP = 40;
 export const NEAR_ROUND_DIGITS = 2;
@@ -24,6 +29,7 @@ export const big = (value = '0') => Big(value);
 export const tsNear2JS = (time) => Math.floor(time/1000000);
 
 export const LOCK_CONTRACT_HASHES = [
+  'F7QrFPx7gRytBVDeSxaWEgfi5JVbwYvyA1mZSW9sU7e7', // v2
   'CNsF8T5rXcnexk5Ac9Roy6mejRbV7XBQvuXdA2FqnkHj', // v1
   'DKUq738xnns9pKjpv9GifM68UoFSmfnBYNp3hsfkkUFa', // v0
 ];
@@ -123,6 +129,28 @@ export const wrapWithTimeout = (promise, timeout_ms) => {
 
 export const withTimeout = (promise) => wrapWithTimeout(promise, 60_000);
 
+// Calls the marketplace method charged to the storage balance of storageAccountId. If the account
+// is not registered or low on storage, the deposit is sent in the same transaction as the call,
+// so it is reverted if the call fails
+export const callWithStorageDeposit = async (contract, storageAccountId, methodName, args, amount = '0', callbackUrl) => {
+  const balance = await contract.storage_balance_of({ account_id: storageAccountId });
+  if (balance && Big(balance.available).gte(STORAGE_AVAILABLE_MIN)) {
+    return contract[methodName]({ args, gas: BOATLOAD_OF_GAS, amount, callbackUrl });
+  }
+
+  const gas = Big(BOATLOAD_OF_GAS).minus(STORAGE_DEPOSIT_GAS).toFixed();
+  return contract.account.signAndSendTransaction({
+    receiverId: contract.contractId,
+    actions: [
+      nearAPI.transactions.functionCall(
+        'storage_deposit', { account_id: storageAccountId }, new BN(STORAGE_DEPOSIT_GAS), new BN(STORAGE_DEPOSIT_AMOUNT)
+      ),
+      nearAPI.transactions.functionCall(methodName, args, new BN(gas), new BN(amount)),
+    ],
+    walletCallbackUrl: callbackUrl && callbackUrl.toString(),
+  });
+};
+
 export const loadListPaginated = async (callback, limit = 200) => {
   let result = [];
   let offset = 0;
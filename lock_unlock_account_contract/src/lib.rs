@@ -1,9 +1,19 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, ext_contract, near_bindgen, AccountId, PanicOnDefault, Promise, PromiseResult, PublicKey,
+    env, ext_contract, log, near_bindgen, AccountId, Balance, PanicOnDefault, Promise,
+    PromiseResult, PublicKey, Timestamp,
 };
 
 const ON_ACCESS_KEY_ADDED_CALLBACK_GAS: u64 = 20_000_000_000_000;
+const LOT_OFFER_GAS: u64 = 30_000_000_000_000;
+const ON_LOT_OFFERED_CALLBACK_GAS: u64 = 10_000_000_000_000;
+const LOT_BUNDLE_JOIN_GAS: u64 = 80_000_000_000_000;
+const ON_BUNDLE_JOINED_CALLBACK_GAS: u64 = 10_000_000_000_000;
+const CLEAR_STATE_GAS: u64 = 5_000_000_000_000;
+/// Key under which near-sdk stores the contract struct
+const STATE_KEY: &[u8] = b"STATE";
 /// Indicates there are no deposit for a callback for better readability
 const NO_DEPOSIT: u128 = 0;
 
@@ -11,11 +21,68 @@ const NO_DEPOSIT: u128 = 0;
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     pub owner_id: AccountId,
+    /// Owner the account was locked to, kept after unlock
+    pub original_owner_id: AccountId,
+    pub lock_timestamp: Timestamp,
+    /// Successful unlocks, kept across relocks of the same account
+    pub unlocks: Vec<UnlockRecord>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UnlockRecord {
+    pub owner_id: AccountId,
+    pub timestamp: U64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockInfo {
+    pub owner_id: AccountId,
+    pub original_owner_id: AccountId,
+    pub lock_timestamp: U64,
+    pub is_unlocked: bool,
+    pub unlocks: Vec<UnlockRecord>,
+}
+
+/// Lot parameters passed to the marketplace `lot_offer`
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LotOfferArgs {
+    pub seller_id: AccountId,
+    pub reserve_price: U128,
+    pub buy_now_price: U128,
+    pub duration: U64,
+    pub sweep_on_claim: Option<bool>,
+}
+
+/// Lot on the marketplace `lot_bundle_join` to join
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LotBundleJoinArgs {
+    pub lot_id: AccountId,
+    pub seller_id: AccountId,
 }
 
 #[ext_contract(ext_self)]
 pub trait ExtContract {
     fn on_access_key_added(&mut self, owner_id: AccountId) -> bool;
+    fn on_lot_offered(&mut self, seller_id: AccountId) -> bool;
+    fn on_bundle_joined(&mut self, seller_id: AccountId) -> bool;
+}
+
+#[ext_contract(ext_marketplace)]
+pub trait ExtMarketplace {
+    fn lot_offer(
+        &mut self,
+        seller_id: AccountId,
+        reserve_price: U128,
+        buy_now_price: U128,
+        finish_timestamp: Option<U64>,
+        duration: Option<U64>,
+        sweep_on_claim: Option<bool>,
+    ) -> bool;
+    fn lot_bundle_join(&mut self, lot_id: AccountId) -> bool;
 }
 
 fn is_promise_success() -> bool {
@@ -32,24 +99,94 @@ fn is_promise_success() -> bool {
 
 #[near_bindgen]
 impl Contract {
+    /// If `lot` is provided, the account is also offered on the marketplace `owner_id`. If the
+    /// offer fails, the ownership is passed to the seller, so the seller could unlock the account.
+    /// The marketplace charges the lot storage to the seller, so the seller must be registered
+    /// with `storage_deposit` on the marketplace beforehand, otherwise the offer fails.
+    /// If `bundle` is provided instead, the account joins the lot `bundle.lot_id` to be sold
+    /// together with it. The marketplace only accepts the join from the account itself, so this
+    /// is how the account consents to it. If the join fails, the ownership is passed to
+    /// `bundle.seller_id`.
     #[init(ignore_state)]
-    pub fn lock(owner_id: AccountId) -> Self {
+    pub fn lock(
+        owner_id: AccountId,
+        lot: Option<LotOfferArgs>,
+        bundle: Option<LotBundleJoinArgs>,
+    ) -> Self {
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
             "Actor is not allowed to init the contract"
         );
+        // state of the previous lock is absent or unreadable if the account wasn't locked with
+        // this version of the contract, history starts from scratch in this case
+        let unlocks = env::storage_read(STATE_KEY)
+            .and_then(|x| Self::try_from_slice(&x).ok())
+            .map(|x| x.unlocks)
+            .unwrap_or_default();
+        if let Some(lot) = lot {
+            ext_marketplace::lot_offer(
+                lot.seller_id.clone(),
+                lot.reserve_price,
+                lot.buy_now_price,
+                None,
+                Some(lot.duration),
+                lot.sweep_on_claim,
+                owner_id.clone(),
+                NO_DEPOSIT,
+                LOT_OFFER_GAS.into(),
+            )
+            .then(ext_self::on_lot_offered(
+                lot.seller_id,
+                env::current_account_id(),
+                NO_DEPOSIT,
+                ON_LOT_OFFERED_CALLBACK_GAS.into(),
+            ));
+        }
+        if let Some(bundle) = bundle {
+            ext_marketplace::lot_bundle_join(
+                bundle.lot_id,
+                owner_id.clone(),
+                NO_DEPOSIT,
+                LOT_BUNDLE_JOIN_GAS.into(),
+            )
+            .then(ext_self::on_bundle_joined(
+                bundle.seller_id,
+                env::current_account_id(),
+                NO_DEPOSIT,
+                ON_BUNDLE_JOINED_CALLBACK_GAS.into(),
+            ));
+        }
         Self {
-            owner_id: owner_id.into(),
+            owner_id: owner_id.clone(),
+            original_owner_id: owner_id,
+            lock_timestamp: env::block_timestamp(),
+            unlocks,
         }
     }
 
-    pub fn unlock(&mut self, public_key: PublicKey) {
+    /// If `new_code` is provided, the lock removes itself: the key is added, the state is cleared
+    /// and `new_code` (possibly empty) is deployed in a single batch. If any of the actions fails,
+    /// the whole batch is reverted and the lock stays in place with the same owner.
+    /// The returned promise fails with the batch, otherwise resolves to false if the key wasn't
+    /// added, so the caller sees the actual outcome of the unlock.
+    pub fn unlock(&mut self, public_key: PublicKey, new_code: Option<Base64VecU8>) -> Promise {
         assert_eq!(
             env::predecessor_account_id(),
             self.owner_id,
             "Actor is not allowed to add a key"
         );
+        if let Some(new_code) = new_code {
+            return Promise::new(env::current_account_id())
+                .add_full_access_key(public_key)
+                .function_call(
+                    "clear_state".to_string(),
+                    vec![],
+                    NO_DEPOSIT,
+                    CLEAR_STATE_GAS.into(),
+                )
+                .deploy_contract(new_code.into());
+        }
         self.owner_id = env::current_account_id();
         Promise::new(env::current_account_id())
             .add_full_access_key(public_key.into())
@@ -58,13 +195,52 @@ impl Contract {
                 env::current_account_id(),
                 NO_DEPOSIT,
                 ON_ACCESS_KEY_ADDED_CALLBACK_GAS.into(),
-            ));
+            ))
+    }
+
+    /// Transfers the liquid balance above `keep_amount` to `beneficiary_id`. The amount required
+    /// for the storage is always kept, returns the swept amount.
+    pub fn sweep_to(&self, beneficiary_id: AccountId, keep_amount: U128) -> U128 {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Actor is not allowed to sweep"
+        );
+        let storage_amount = Balance::from(env::storage_usage()) * env::storage_byte_cost();
+        let keep_amount = std::cmp::max(keep_amount.0, storage_amount);
+        let amount = env::account_balance().saturating_sub(keep_amount);
+        if amount > 0 {
+            Promise::new(beneficiary_id.clone()).transfer(amount);
+        }
+        log!("sweep_to: swept {} to {}", amount, beneficiary_id);
+
+        amount.into()
     }
 
     pub fn get_owner(&self) -> AccountId {
         self.owner_id.clone()
     }
 
+    pub fn get_lock_info(&self) -> LockInfo {
+        LockInfo {
+            owner_id: self.owner_id.clone(),
+            original_owner_id: self.original_owner_id.clone(),
+            lock_timestamp: self.lock_timestamp.into(),
+            is_unlocked: self.owner_id == env::current_account_id(),
+            unlocks: self.unlocks.clone(),
+        }
+    }
+
+    // Part of the self-removing unlock batch
+    pub fn clear_state() {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "State can only be cleared by the contract"
+        );
+        env::storage_remove(STATE_KEY);
+    }
+
     // Callback
     pub fn on_access_key_added(&mut self, owner_id: AccountId) -> bool {
         assert_eq!(
@@ -73,10 +249,57 @@ impl Contract {
             "Callback can only be called from the contract"
         );
         let access_key_created = is_promise_success();
-        if !access_key_created {
+        if access_key_created {
+            self.unlocks.push(UnlockRecord {
+                owner_id,
+                timestamp: env::block_timestamp().into(),
+            });
+        } else {
             // In case of failure, put owner_id back
             self.owner_id = owner_id;
         }
         access_key_created
     }
+
+    // Callback
+    pub fn on_lot_offered(&mut self, seller_id: AccountId) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let lot_offered = is_promise_success();
+        if !lot_offered {
+            log!("on_lot_offered: lot offer failed, owner is {}", seller_id);
+            self.owner_id = seller_id;
+        }
+        lot_offered
+    }
+
+    // Callback
+    pub fn on_bundle_joined(&mut self, seller_id: AccountId) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        // the marketplace resolves to false if the account wasn't joined
+        let bundle_joined = match env::promise_result(0) {
+            PromiseResult::Successful(x) => x == b"true",
+            _ => false,
+        };
+        if !bundle_joined {
+            log!(
+                "on_bundle_joined: bundle join failed, owner is {}",
+                seller_id
+            );
+            self.owner_id = seller_id;
+        }
+        bundle_joined
+    }
 }